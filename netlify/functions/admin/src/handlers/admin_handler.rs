@@ -4,37 +4,106 @@ use aws_lambda_events::{
     apigw::ApiGatewayProxyResponse,
     http::{HeaderValue, StatusCode},
 };
-use bcrypt::verify;
-use cookie::{time::Duration, Cookie, CookieJar, Key};
+use chrono::Utc;
 use lambda_runtime::Error;
 use mongodb::{
-    bson::{doc, from_document, oid::ObjectId},
+    bson::{doc, to_bson, to_document, Bson, Document},
     Database,
 };
 use serde_json::json;
 use shared_lib::{
+    auth::{context::AuthContext, permission::Permission},
+    error::AppError,
     models::{
-        post::Post,
+        audit_event::{AuditAction, AuditEvent},
+        post::{Post, PostSummary},
         user::{User, UserRole},
     },
     traits::model_traits::ModelTraits,
-    AppErrorResponse, AppSuccessResponse, DataInsertError,
+    utils::{
+        cookie::{session_cookie, CookieKeys},
+        headers::RequestMetadata,
+        password_hasher::PasswordHasher,
+    },
+    AppSuccessResponse,
 };
-use validator::HasLen;
+use validator::Validate;
 
 use crate::UserLoginData;
 
 pub async fn login_admin(
     database: &Database,
     user_login_data: UserLoginData,
+    request_metadata: &RequestMetadata,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let cookie_secret = env::var("COOKIE_SECRET").unwrap_or_default();
-    let cookie_name = env::var("COOKIE_NAME").unwrap_or_default();
+    match verify_admin_credentials(database, &user_login_data, request_metadata, "password").await {
+        Ok(db_user) => create_session_response(&db_user),
+        Err(response) => Ok(response),
+    }
+}
+
+/// Looks up the user by username, checks the password and account state and
+/// records the attempt in the audit log.
+pub async fn verify_admin_credentials(
+    database: &Database,
+    user_login_data: &UserLoginData,
+    request_metadata: &RequestMetadata,
+    login_method: &str,
+) -> Result<User, ApiGatewayProxyResponse> {
+    let result = check_admin_credentials(database, user_login_data).await;
+
+    login_audit_event(
+        result.as_ref().ok(),
+        user_login_data.username.clone(),
+        login_method,
+        request_metadata,
+    )
+    .record(database)
+    .await;
+
+    result
+}
+
+/// Audit entry for a login attempt. `db_user` is `None` when the attempt failed.
+pub fn login_audit_event(
+    db_user: Option<&User>,
+    login_name: Option<String>,
+    login_method: &str,
+    request_metadata: &RequestMetadata,
+) -> AuditEvent {
+    let action = if db_user.is_some() {
+        AuditAction::Login
+    } else {
+        AuditAction::LoginFailed
+    };
+
+    AuditEvent {
+        actor_id: db_user.and_then(|db_user| db_user.id),
+        action: Some(action),
+        target_type: Some("user".to_owned()),
+        target_id: db_user
+            .and_then(|db_user| db_user.id)
+            .map(|user_id| user_id.to_hex()),
+        after: Some(doc! {"login_name": login_name, "method": login_method}),
+        ..Default::default()
+    }
+    .with_request_metadata(request_metadata)
+}
 
+async fn check_admin_credentials(
+    database: &Database,
+    user_login_data: &UserLoginData,
+) -> Result<User, ApiGatewayProxyResponse> {
     let username = user_login_data.username.clone().unwrap_or_default();
     let password = user_login_data.password.clone().unwrap_or_default();
 
-    let user_from_db_result = User::find(
+    let invalid_credentials = || {
+        AppError::NotFound("Error. Make sure username or password is correct".to_string())
+            .into_response()
+            .unwrap_or_default()
+    };
+
+    let user_from_db_result = User::find::<User>(
         database,
         doc! {"username": username.clone()},
         Some(doc! {"created_at": false, "updated_at": false}),
@@ -43,129 +112,303 @@ pub async fn login_admin(
     )
     .await;
 
-    match user_from_db_result {
-        Ok(data_from_db) => {
-            if data_from_db.length() == 0 {
-                return AppErrorResponse::new(
-                    StatusCode::NOT_FOUND,
-                    Some("Error. Make sure username or password is correct".to_string()),
-                    None,
-                );
-            }
+    let db_user = match user_from_db_result {
+        Ok(users) => match users.into_iter().next() {
+            Some(user) => user,
+            None => return Err(invalid_credentials()),
+        },
+        Err(error) => {
+            eprintln!("Failed to read user for login: {}", error);
+            return Err(AppError::from(error).into_response().unwrap_or_default());
+        }
+    };
 
-            let db_user: User = from_document::<User>(data_from_db[0].clone()).unwrap_or_default();
+    let password_hasher = PasswordHasher::from_env();
+    let hashed_password_from_db = db_user.password.clone().unwrap_or_default();
 
-            if db_user.role.clone().unwrap_or(UserRole::User) != UserRole::Admin {
-                return AppErrorResponse::new(
-                    StatusCode::UNAUTHORIZED,
-                    Some("Unauthorized login request".to_string()),
-                    None,
-                );
-            }
+    if !password_hasher.verify(&password, &hashed_password_from_db) {
+        return Err(invalid_credentials());
+    }
 
-            let hashed_password_from_db = db_user.password.clone().unwrap_or_default();
-            let password_is_valid = verify(password, &hashed_password_from_db).unwrap_or_default();
+    // The plain password is only available now, so upgrade outdated hashes while we have it.
+    if password_hasher.needs_rehash(&hashed_password_from_db) {
+        if let Err(error) = rehash_password(database, &db_user, &password, &password_hasher).await {
+            eprintln!("Failed to upgrade password hash: {}", error);
+        }
+    }
 
-            if !password_is_valid {
-                return AppErrorResponse::new(
-                    StatusCode::NOT_FOUND,
-                    Some("Error. Make sure username or password is correct".to_string()),
-                    None,
-                );
-            }
+    if let Some(response) = sign_in_rejection(&db_user) {
+        return Err(response);
+    }
 
-            let key = Key::from(cookie_secret.as_bytes());
-            // Add a private (signed + encrypted) cookie.
-            let mut jar = CookieJar::new();
-            let mut cookie = Cookie::new(
-                cookie_name.clone(),
-                db_user.username.clone().unwrap_or_default(),
-            );
-            cookie.set_http_only(true);
-            cookie.set_secure(true);
-            cookie.set_path("/");
-            cookie.set_max_age(Duration::days(30));
-            jar.private_mut(&key).add(cookie);
-
-            // The cookie's contents are encrypted.
-            let cookie_value = jar.get(&cookie_name).unwrap().to_string();
-
-            let mut response = AppSuccessResponse::new(
-                StatusCode::OK,
-                Some("Login successful".to_string()),
-                Some(json!({"user": db_user.username})),
-            )
-            .unwrap_or_default();
+    Ok(db_user)
+}
 
-            response
-                .headers
-                .insert("Set-Cookie", HeaderValue::from_str(&cookie_value).unwrap());
+async fn rehash_password(
+    database: &Database,
+    db_user: &User,
+    password: &str,
+    password_hasher: &PasswordHasher,
+) -> Result<(), Error> {
+    let hashed_password = password_hasher.hash(password)?;
 
-            return Ok(response);
-        }
-        Err(_) => {
-            return AppErrorResponse::new(
-                StatusCode::NOT_FOUND,
-                Some("Error. Make sure username or password is correct".to_string()),
-                None,
-            );
-        }
-    };
+    User::update_one(
+        database,
+        doc! {"_id": db_user.id, "password": db_user.password.clone()},
+        doc! {"$set": {
+            "password": hashed_password,
+            "updated_at": to_bson(&Utc::now()).unwrap_or_default(),
+        }},
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the error response if an already identified user may not start an admin session.
+pub fn sign_in_rejection(db_user: &User) -> Option<ApiGatewayProxyResponse> {
+    if !db_user
+        .role
+        .clone()
+        .unwrap_or(UserRole::User)
+        .has_permission(Permission::AccessAdmin)
+    {
+        return AppError::Unauthorized("Unauthorized login request".to_string())
+            .into_response()
+            .ok();
+    }
+
+    // Accounts from before verification existed are marked verified by the
+    // `0002_backfill_email_verified_at` migration, which `init_db` runs at
+    // cold start.
+    let require_email_verification = env::var("REQUIRE_EMAIL_VERIFICATION")
+        .map(|value| value.to_lowercase() != "false")
+        .unwrap_or(true);
+
+    if require_email_verification && db_user.email_verified_at.is_none() {
+        return AppError::Forbidden("Verify your email address before logging in".to_string())
+            .into_response()
+            .ok();
+    }
+
+    if !db_user.is_active() {
+        return AppError::Forbidden("This account has been deactivated".to_string())
+            .into_response()
+            .ok();
+    }
+
+    if db_user.password_reset_required.unwrap_or_default() {
+        return AppError::Forbidden(
+            "A password reset is required. Check your email for a reset link".to_string(),
+        )
+        .into_response()
+        .ok();
+    }
+
+    None
+}
+
+/// Responds with the encrypted session cookie for `db_user`.
+pub fn create_session_response(db_user: &User) -> Result<ApiGatewayProxyResponse, Error> {
+    let cookie_keys = CookieKeys::from_env()?;
+
+    // The cookie's contents are encrypted with the newest key.
+    let cookie_value =
+        cookie_keys.encrypt(session_cookie(db_user.username.clone().unwrap_or_default()));
+
+    let mut response = AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Login successful".to_string()),
+        Some(json!({"user": db_user.username})),
+    )
+    .unwrap_or_default();
+
+    response
+        .headers
+        .insert("Set-Cookie", HeaderValue::from_str(&cookie_value).unwrap());
+
+    Ok(response)
 }
 
 pub async fn add_post(
     database: &Database,
+    auth_context: &AuthContext,
     new_post_data: Post,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let result = new_post_data.save(&database).await;
+    let is_published = new_post_data.is_published.unwrap_or_default();
+
+    if is_published && !auth_context.can(Permission::PublishPost) {
+        return AppError::Forbidden("You do not have permission to publish posts".to_string())
+            .into_response();
+    }
 
-    let _ = match result {
+    let new_post_data = Post {
+        // Always the caller, whatever the client sent, so posts cannot be
+        // attributed to someone else.
+        published_by: Some(vec![auth_context.user_id]),
+        created_at: Some(Utc::now()),
+        updated_at: Some(Utc::now()),
+        ..new_post_data
+    };
+
+    let result = new_post_data.save(database).await;
+
+    match result {
         Ok(_insert_value) => {
-            return AppSuccessResponse::new(
+            let slug = new_post_data.slug.clone().unwrap_or_default();
+
+            AuditEvent {
+                after: Some(doc! {
+                    "title": new_post_data.title.clone(),
+                    "is_published": is_published,
+                }),
+                ..post_audit_event(auth_context, &slug, AuditAction::PostCreated)
+            }
+            .record(database)
+            .await;
+
+            if is_published {
+                post_audit_event(auth_context, &slug, AuditAction::PostPublished)
+                    .record(database)
+                    .await;
+            }
+
+            AppSuccessResponse::new(
                 StatusCode::OK,
                 Some("Data added successfully".to_string()),
                 None,
             )
         }
-        Err(error) => match error {
-            DataInsertError::FieldValidationError(error) => {
-                return AppErrorResponse::new(
-                    StatusCode::BAD_REQUEST,
-                    Some("An error occured".to_string()),
-                    Some(json!({
-                        "errors": error
-                    })),
-                )
-            }
-            DataInsertError::MongoDuplicateError(mut error) if error.code == 11000 => {
-                let duplicate_field_data =
-                    error.message.split("key: ").collect::<Vec<&str>>()[1].to_owned();
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+pub async fn update_post(
+    database: &Database,
+    auth_context: &AuthContext,
+    post_changes: Post,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let slug = post_changes.slug.clone().unwrap_or_default();
 
-                let duplicate_field_value =
-                    duplicate_field_data.split(" ").collect::<Vec<&str>>()[2].to_owned();
+    let existing_post = match Post::find_one::<Post>(database, doc! {"slug": &slug}, None).await {
+        Ok(existing_post) => existing_post,
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let existing_post = match existing_post {
+        Some(post) => post,
+        None => return AppError::NotFound("Post not found".to_string()).into_response(),
+    };
+
+    let changes_publication = post_changes
+        .is_published
+        .is_some_and(|is_published| Some(is_published) != existing_post.is_published);
+
+    if changes_publication && !auth_context.can(Permission::PublishPost) {
+        return AppError::Forbidden("You do not have permission to publish posts".to_string())
+            .into_response();
+    }
+
+    let previous_post = to_document(&existing_post)?;
+
+    let updated_post = Post {
+        title: post_changes.title.or(existing_post.title),
+        slug: existing_post.slug,
+        rust_code_snippet: post_changes
+            .rust_code_snippet
+            .or(existing_post.rust_code_snippet),
+        content: post_changes.content.or(existing_post.content),
+        published_by: existing_post.published_by,
+        tags: post_changes.tags.or(existing_post.tags),
+        code_snippet_enabled: post_changes
+            .code_snippet_enabled
+            .or(existing_post.code_snippet_enabled),
+        playground_enabled: post_changes
+            .playground_enabled
+            .or(existing_post.playground_enabled),
+        is_published: post_changes.is_published.or(existing_post.is_published),
+        is_featured: post_changes.is_featured.or(existing_post.is_featured),
+        created_at: existing_post.created_at,
+        updated_at: Some(Utc::now()),
+    };
+
+    if let Err(errors) = updated_post.validate() {
+        return AppError::from(errors).into_response();
+    }
+
+    let update_result = Post::update_one(
+        database,
+        doc! {"slug": &slug},
+        doc! {"$set": to_document(&updated_post)?},
+    )
+    .await;
 
-                error.message =
-                    format!("An error occured. {} already exists", duplicate_field_value);
+    match update_result {
+        Ok(_) => {
+            let (before, after) = changed_fields(&previous_post, &to_document(&updated_post)?);
 
-                return AppErrorResponse::new(StatusCode::BAD_REQUEST, Some(error.message), None);
+            AuditEvent {
+                before: Some(before),
+                after: Some(after),
+                ..post_audit_event(auth_context, &slug, AuditAction::PostUpdated)
             }
-            _ => {
-                return AppErrorResponse::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Some("An error occured".to_string()),
-                    None,
-                )
+            .record(database)
+            .await;
+
+            if changes_publication {
+                let action = if updated_post.is_published.unwrap_or_default() {
+                    AuditAction::PostPublished
+                } else {
+                    AuditAction::PostUnpublished
+                };
+
+                post_audit_event(auth_context, &slug, action)
+                    .record(database)
+                    .await;
             }
-        },
-    };
+
+            AppSuccessResponse::new(
+                StatusCode::OK,
+                Some("Data updated successfully".to_string()),
+                None,
+            )
+        }
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+fn post_audit_event(auth_context: &AuthContext, slug: &str, action: AuditAction) -> AuditEvent {
+    AuditEvent {
+        action: Some(action),
+        target_type: Some("post".to_owned()),
+        target_id: Some(slug.to_owned()),
+        ..AuditEvent::by(auth_context)
+    }
+}
+
+/// The fields that differ between two versions of a document, ignoring timestamps.
+fn changed_fields(before: &Document, after: &Document) -> (Document, Document) {
+    let mut changed_before = Document::new();
+    let mut changed_after = Document::new();
+
+    for (key, value) in after.iter() {
+        if key == "updated_at" || before.get(key) == Some(value) {
+            continue;
+        }
+
+        changed_before.insert(key, before.get(key).cloned().unwrap_or(Bson::Null));
+        changed_after.insert(key, value.clone());
+    }
+
+    (changed_before, changed_after)
 }
 
 pub async fn get_posts(
     database: &Database,
     current_page: Option<i64>,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let post_response = Post::find_paginated(
-        &database,
+    let post_response = Post::find_paginated::<PostSummary>(
+        database,
         doc! {"is_published": true},
         Some(doc! {"title": true, "slug": true, "tags": true, "created_at": true, "_id": false}),
         Some(doc! { "created_at": -1 }),
@@ -191,11 +434,7 @@ pub async fn get_posts(
             )
         }
 
-        Err(_) => AppSuccessResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Some("An error occured fetching data".to_string()),
-            None,
-        ),
+        Err(error) => AppError::from(error).into_response(),
     }
 }
 
@@ -203,16 +442,16 @@ pub async fn get_post_by_slug(
     database: &Database,
     slug: String,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let post_response = Post::find_one(
-        &database,
+    let post_response = Post::find_one::<Post>(
+        database,
         doc! {"slug": slug, "is_published": true},
         Some(doc! {"_id": false}),
-        1,
     )
     .await;
 
     match post_response {
-        Ok(document) => AppSuccessResponse::new(
+        Ok(None) => AppError::NotFound("Post not found".to_string()).into_response(),
+        Ok(Some(document)) => AppSuccessResponse::new(
             StatusCode::OK,
             Some("Request successful".to_string()),
             Some(json!({
@@ -220,17 +459,13 @@ pub async fn get_post_by_slug(
             })),
         ),
 
-        Err(_) => AppSuccessResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Some("An error occured fetching data".to_string()),
-            None,
-        ),
+        Err(error) => AppError::from(error).into_response(),
     }
 }
 
 pub async fn get_featured_posts(database: &Database) -> Result<ApiGatewayProxyResponse, Error> {
-    let featured_post_response = Post::find(
-        &database,
+    let featured_post_response = Post::find::<PostSummary>(
+        database,
         doc! {"is_featured": true, "is_published": true},
         Some(doc! {"title": true, "slug": true, "tags": true, "updated_at": true, "_id": false}),
         Some(doc! { "updated_at": -1 }),
@@ -260,10 +495,6 @@ pub async fn get_featured_posts(database: &Database) -> Result<ApiGatewayProxyRe
             Some(serde_json::to_value(documents).unwrap_or_default()),
         ),
 
-        Err(_) => AppSuccessResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Some("An error occured fetching data".to_string()),
-            None,
-        ),
+        Err(error) => AppError::from(error).into_response(),
     }
 }
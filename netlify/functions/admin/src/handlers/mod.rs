@@ -1 +1,3 @@
-pub mod admin_handler;
\ No newline at end of file
+pub mod admin_handler;
+pub mod oidc_handler;
+pub mod token_handler;
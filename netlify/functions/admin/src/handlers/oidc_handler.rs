@@ -0,0 +1,229 @@
+use std::env;
+
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    http::{
+        header::{LOCATION, SET_COOKIE},
+        HeaderValue, StatusCode,
+    },
+};
+use chrono::Utc;
+use cookie::{time::Duration, Cookie, SameSite};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, to_bson},
+    Database,
+};
+use reqwest::Client;
+use shared_lib::{
+    error::AppError,
+    models::user::User,
+    router::extract::CookieName,
+    traits::model_traits::ModelTraits,
+    utils::{cookie::CookieKeys, headers::RequestMetadata, token::generate_token},
+    AppSuccessResponse,
+};
+
+use crate::{
+    handlers::admin_handler::{create_session_response, login_audit_event, sign_in_rejection},
+    oidc::{
+        authorization_url, discover, exchange_code, fetch_jwks, validate_id_token, OidcConfig,
+        OidcFlowState,
+    },
+    OidcCallbackQueryParams,
+};
+
+const OIDC_FLOW_COOKIE_PATH: &str = "/api/admin/oidc";
+
+/// Name of the short-lived cookie holding the state, nonce and PKCE verifier.
+pub fn oidc_flow_cookie_name() -> String {
+    format!("{}_oidc", env::var("COOKIE_NAME").unwrap_or_default())
+}
+
+/// Extracts the flow cookie set by [`start_oidc_login`].
+pub struct OidcFlowCookie;
+
+impl CookieName for OidcFlowCookie {
+    fn name() -> String {
+        oidc_flow_cookie_name()
+    }
+}
+
+/// Redirects the browser to the identity provider.
+pub async fn start_oidc_login() -> Result<ApiGatewayProxyResponse, Error> {
+    let oidc_config = OidcConfig::from_env();
+
+    if !oidc_config.is_configured() {
+        return AppError::NotFound("Single sign-on is not configured".to_string()).into_response();
+    }
+
+    let client = Client::new();
+    let provider_metadata = discover(&client, &oidc_config).await?;
+
+    let flow_state = OidcFlowState {
+        state: generate_token(),
+        nonce: generate_token(),
+        code_verifier: generate_token(),
+    };
+
+    let authorization_url = authorization_url(&oidc_config, &provider_metadata, &flow_state)?;
+
+    let mut response = AppSuccessResponse::new(StatusCode::FOUND, None, None).unwrap_or_default();
+
+    response
+        .headers
+        .insert(LOCATION, HeaderValue::from_str(&authorization_url)?);
+    response
+        .headers
+        .insert(SET_COOKIE, create_flow_cookie(&flow_state)?);
+
+    Ok(response)
+}
+
+/// Handles the provider's redirect back and starts a session for the matching user.
+pub async fn complete_oidc_login(
+    database: &Database,
+    oidc_callback_query_params: OidcCallbackQueryParams,
+    flow_state: Option<OidcFlowState>,
+    request_metadata: &RequestMetadata,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    if oidc_callback_query_params.error.is_some() {
+        return AppError::Unauthorized(
+            "Sign-in was cancelled or rejected by the identity provider".to_string(),
+        )
+        .into_response();
+    }
+
+    let flow_state = flow_state
+        .filter(|flow_state| Some(&flow_state.state) == oidc_callback_query_params.state.as_ref());
+
+    let (flow_state, code) = match (flow_state, oidc_callback_query_params.code) {
+        (Some(flow_state), Some(code)) => (flow_state, code),
+        _ => {
+            return AppError::BadRequest(
+                "Sign-in session has expired. Please try again".to_string(),
+            )
+            .into_response()
+        }
+    };
+
+    let oidc_config = OidcConfig::from_env();
+    let client = Client::new();
+    let provider_metadata = discover(&client, &oidc_config).await?;
+
+    let id_token = exchange_code(
+        &client,
+        &oidc_config,
+        &provider_metadata,
+        &code,
+        &flow_state.code_verifier,
+    )
+    .await?;
+    let jwks = fetch_jwks(&client, &provider_metadata).await?;
+
+    let claims = match validate_id_token(
+        &id_token,
+        &jwks,
+        &provider_metadata.issuer,
+        &oidc_config.client_id,
+        &flow_state.nonce,
+    ) {
+        Ok(claims) => claims,
+        Err(_) => {
+            return AppError::Unauthorized("Identity token could not be verified".to_string())
+                .into_response()
+        }
+    };
+
+    let email = match (claims.email, claims.email_verified) {
+        (Some(email), Some(true)) => email,
+        _ => {
+            return AppError::Forbidden(
+                "Your identity provider has not verified this email address".to_string(),
+            )
+            .into_response()
+        }
+    };
+
+    let db_user = User::find::<User>(database, doc! {"email": &email}, None, None, 1)
+        .await?
+        .into_iter()
+        .next();
+
+    let mut db_user = match db_user {
+        Some(db_user) => db_user,
+        None => {
+            login_audit_event(None, Some(email), "oidc", request_metadata)
+                .record(database)
+                .await;
+
+            return AppError::Unauthorized(
+                "No account is linked to this email address".to_string(),
+            )
+            .into_response();
+        }
+    };
+
+    // The identity provider has verified the address, so local verification is not needed.
+    if db_user.email_verified_at.is_none() {
+        let now = Utc::now();
+
+        User::update_one(
+            database,
+            doc! {"_id": db_user.id},
+            doc! {"$set": {"email_verified_at": to_bson(&now).unwrap_or_default()}},
+        )
+        .await?;
+
+        db_user.email_verified_at = Some(now);
+    }
+
+    if let Some(response) = sign_in_rejection(&db_user) {
+        login_audit_event(None, Some(email), "oidc", request_metadata)
+            .record(database)
+            .await;
+
+        return Ok(response);
+    }
+
+    login_audit_event(Some(&db_user), Some(email), "oidc", request_metadata)
+        .record(database)
+        .await;
+
+    let post_login_redirect_url = env::var("OIDC_POST_LOGIN_REDIRECT_URL")
+        .unwrap_or(env::var("FRONTEND_BASE_URL").unwrap_or("/".to_owned()));
+
+    let mut response = create_session_response(&db_user)?;
+
+    response.status_code = StatusCode::FOUND.as_u16() as i64;
+    response
+        .headers
+        .insert(LOCATION, HeaderValue::from_str(&post_login_redirect_url)?);
+    response
+        .multi_value_headers
+        .append(SET_COOKIE, remove_flow_cookie()?);
+
+    Ok(response)
+}
+
+fn create_flow_cookie(flow_state: &OidcFlowState) -> Result<HeaderValue, Error> {
+    let cookie_keys = CookieKeys::from_env()?;
+
+    let mut cookie = Cookie::new(oidc_flow_cookie_name(), serde_json::to_string(flow_state)?);
+    cookie.set_http_only(true);
+    cookie.set_secure(true);
+    // Lax so the cookie survives the top-level redirect back from the provider.
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_path(OIDC_FLOW_COOKIE_PATH);
+    cookie.set_max_age(Duration::minutes(10));
+
+    Ok(HeaderValue::from_str(&cookie_keys.encrypt(cookie))?)
+}
+
+fn remove_flow_cookie() -> Result<HeaderValue, Error> {
+    let mut cookie = Cookie::new(oidc_flow_cookie_name(), "");
+    cookie.set_path(OIDC_FLOW_COOKIE_PATH);
+    cookie.make_removal();
+
+    Ok(HeaderValue::from_str(&cookie.to_string())?)
+}
@@ -0,0 +1,190 @@
+use std::env;
+
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use chrono::{Duration, Utc};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, to_bson, DateTime as BsonDateTime},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    auth::{context::AuthContext, jwt::JwtConfig},
+    error::AppError,
+    models::{refresh_token::RefreshToken, user::User},
+    traits::model_traits::ModelTraits,
+    utils::{
+        headers::RequestMetadata,
+        token::{generate_identifier, generate_token, hash_token},
+    },
+    AppSuccessResponse,
+};
+
+use crate::{
+    handlers::admin_handler::{sign_in_rejection, verify_admin_credentials},
+    RefreshTokenData, UserLoginData,
+};
+
+pub async fn create_token(
+    database: &Database,
+    user_login_data: UserLoginData,
+    request_metadata: &RequestMetadata,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    match verify_admin_credentials(database, &user_login_data, request_metadata, "token").await {
+        Ok(db_user) => issue_token_pair(database, &db_user, generate_identifier(24)).await,
+        Err(response) => Ok(response),
+    }
+}
+
+pub async fn refresh_token(
+    database: &Database,
+    refresh_token_data: RefreshTokenData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let token_hash = hash_token(&refresh_token_data.refresh_token.unwrap_or_default());
+
+    let stored_token = match find_refresh_token(database, &token_hash).await? {
+        Some(stored_token) => stored_token,
+        None => return invalid_refresh_token(),
+    };
+
+    let family_id = stored_token.family_id.clone().unwrap_or_default();
+
+    if stored_token.used_at.is_some() || stored_token.revoked_at.is_some() {
+        // A rotated token was presented again, so it has leaked: end the whole session.
+        revoke_family(database, &family_id).await?;
+
+        return invalid_refresh_token();
+    }
+
+    if stored_token.is_expired() {
+        return invalid_refresh_token();
+    }
+
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    let claim_result = RefreshToken::update_one(
+        database,
+        doc! {"_id": stored_token.id, "used_at": null, "revoked_at": null},
+        doc! {"$set": {"used_at": now.clone(), "updated_at": now}},
+    )
+    .await?;
+
+    if claim_result.modified_count != 1 {
+        // Another request rotated this token at the same time.
+        revoke_family(database, &family_id).await?;
+
+        return invalid_refresh_token();
+    }
+
+    let db_user = User::find::<User>(database, doc! {"_id": stored_token.user_id}, None, None, 1)
+        .await?
+        .into_iter()
+        .next();
+
+    let db_user = match db_user {
+        Some(db_user) => db_user,
+        None => return invalid_refresh_token(),
+    };
+
+    if let Some(response) = sign_in_rejection(&db_user) {
+        revoke_family(database, &family_id).await?;
+
+        return Ok(response);
+    }
+
+    issue_token_pair(database, &db_user, family_id).await
+}
+
+pub async fn revoke_refresh_token(
+    database: &Database,
+    refresh_token_data: RefreshTokenData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let token_hash = hash_token(&refresh_token_data.refresh_token.unwrap_or_default());
+
+    if let Some(stored_token) = find_refresh_token(database, &token_hash).await? {
+        revoke_family(database, &stored_token.family_id.unwrap_or_default()).await?;
+    }
+
+    AppSuccessResponse::new(StatusCode::OK, Some("Logged out".to_string()), None)
+}
+
+async fn issue_token_pair(
+    database: &Database,
+    db_user: &User,
+    family_id: String,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user_id = match db_user.id {
+        Some(user_id) => user_id,
+        None => return invalid_refresh_token(),
+    };
+
+    let jwt_config = JwtConfig::from_env();
+    let auth_context = AuthContext::for_session(user_id, db_user.role.clone().unwrap_or_default());
+    let access_token = jwt_config.issue_access_token(&auth_context)?;
+
+    let refresh_token_ttl_days = env::var("REFRESH_TOKEN_TTL_DAYS")
+        .unwrap_or_default()
+        .parse::<i64>()
+        .unwrap_or(30);
+
+    let refresh_token = generate_token();
+    let expires_at = Utc::now() + Duration::days(refresh_token_ttl_days);
+
+    RefreshToken {
+        user_id: Some(user_id),
+        family_id: Some(family_id),
+        token_hash: Some(hash_token(&refresh_token)),
+        expires_at: Some(expires_at),
+        purge_at: Some(BsonDateTime::from_millis(expires_at.timestamp_millis())),
+        ..Default::default()
+    }
+    .save(database)
+    .await
+    .map_err(|_| Error::from("Could not store refresh token"))?;
+
+    AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Login successful".to_string()),
+        Some(json!({
+            "access_token": access_token,
+            "token_type": "Bearer",
+            "expires_in": jwt_config.access_token_ttl_seconds,
+            "refresh_token": refresh_token,
+        })),
+    )
+}
+
+async fn find_refresh_token(
+    database: &Database,
+    token_hash: &str,
+) -> Result<Option<RefreshToken>, Error> {
+    let stored_token = RefreshToken::find::<RefreshToken>(
+        database,
+        doc! {"token_hash": token_hash},
+        None,
+        None,
+        1,
+    )
+    .await?
+    .into_iter()
+    .next();
+
+    Ok(stored_token)
+}
+
+async fn revoke_family(database: &Database, family_id: &str) -> Result<(), Error> {
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    RefreshToken::update_many(
+        database,
+        doc! {"family_id": family_id, "revoked_at": null},
+        doc! {"$set": {"revoked_at": now.clone(), "updated_at": now}},
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn invalid_refresh_token() -> Result<ApiGatewayProxyResponse, Error> {
+    AppError::Unauthorized("Refresh token is invalid or has expired".to_string()).into_response()
+}
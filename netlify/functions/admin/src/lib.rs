@@ -1,9 +1,201 @@
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use lambda_runtime::Error;
 use serde::{Deserialize, Serialize};
+use shared_lib::{
+    auth::{csrf::csrf_token_response, permission::Permission},
+    error::AppError,
+    models::post::Post,
+    router::{
+        context::RequestContext,
+        extract::{Cookie, Json, Query},
+        request::RouteRequest,
+        Router,
+    },
+    utils::cookie::SessionCookie,
+    AppSuccessResponse,
+};
+
+use crate::handlers::{
+    admin_handler::{add_post, login_admin, update_post},
+    oidc_handler::{complete_oidc_login, start_oidc_login, OidcFlowCookie},
+    token_handler::{create_token, refresh_token, revoke_refresh_token},
+};
 
 pub mod handlers;
+pub mod oidc;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct UserLoginData {
     pub username: Option<String>,
     pub password: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RefreshTokenData {
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OidcCallbackQueryParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+async fn login(
+    request: RouteRequest,
+    context: RequestContext,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    if let Some(Cookie(token, _)) = request.extract::<Option<Cookie<SessionCookie>>>()? {
+        return AppSuccessResponse::new(StatusCode::FOUND, Some(token), None);
+    };
+
+    let Json(user_login_data) = request.extract::<Json<UserLoginData>>()?;
+
+    if user_login_data.username.is_none() && user_login_data.password.is_none() {
+        return AppError::Unauthorized("Username and password are required".to_owned())
+            .into_response();
+    };
+
+    if user_login_data.username.is_none() {
+        return AppError::BadRequest("Username is required".to_owned()).into_response();
+    };
+
+    if user_login_data.password.is_none() {
+        return AppError::BadRequest("Password is required".to_owned()).into_response();
+    };
+
+    login_admin(
+        &context.database,
+        user_login_data,
+        &context.request_metadata,
+    )
+    .await
+}
+
+/// Every admin route. Login, token and OIDC routes are open to everyone.
+pub fn router() -> Router<RequestContext> {
+    Router::<RequestContext>::new()
+        .get("/api/admin/csrf-token", |_, _| async {
+            csrf_token_response()
+        })
+        .post("/api/admin", login)
+        .post("/api/admin/login", login)
+        .get("/api/admin/oidc/login", |_, _| start_oidc_login())
+        .get("/api/admin/oidc/callback", |request, context| async move {
+            let Query(oidc_callback_query_params) =
+                request.extract::<Query<OidcCallbackQueryParams>>()?;
+            let flow_cookie = request.extract::<Option<Cookie<OidcFlowCookie, _>>>()?;
+
+            complete_oidc_login(
+                &context.database,
+                oidc_callback_query_params,
+                flow_cookie.map(|Cookie(flow_state, _)| flow_state),
+                &context.request_metadata,
+            )
+            .await
+        })
+        .post("/api/admin/token", |request, context| async move {
+            let Json(user_login_data) = request.extract::<Json<UserLoginData>>()?;
+
+            create_token(
+                &context.database,
+                user_login_data,
+                &context.request_metadata,
+            )
+            .await
+        })
+        .post("/api/admin/token/refresh", |request, context| async move {
+            let Json(refresh_token_data) = request.extract::<Json<RefreshTokenData>>()?;
+
+            refresh_token(&context.database, refresh_token_data).await
+        })
+        .post("/api/admin/token/revoke", |request, context| async move {
+            let Json(refresh_token_data) = request.extract::<Json<RefreshTokenData>>()?;
+
+            revoke_refresh_token(&context.database, refresh_token_data).await
+        })
+        .post_guarded(
+            "/api/admin/posts",
+            Permission::CreatePost,
+            |request, context, auth_context| async move {
+                let Json(new_post_data) = request.extract::<Json<Post>>()?;
+
+                add_post(&context.database, &auth_context, new_post_data).await
+            },
+        )
+        .put_guarded(
+            "/api/admin/posts",
+            Permission::EditPost,
+            |request, context, auth_context| async move {
+                let Json(post_changes) = request.extract::<Json<Post>>()?;
+
+                update_post(&context.database, &auth_context, post_changes).await
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::http::Method;
+    use shared_lib::{models::user::UserRole, router::Access};
+
+    use super::*;
+
+    const ROUTES: &[(Method, &str, [bool; 3])] = &[
+        // allowed for [super_admin, admin, user]
+        (Method::POST, "/api/admin/posts", [true, true, false]),
+        (Method::PUT, "/api/admin/posts", [true, true, false]),
+    ];
+
+    #[test]
+    fn every_role_and_route_is_authorized_as_expected() {
+        let router = router();
+        let roles = [UserRole::SuperAdmin, UserRole::Admin, UserRole::User];
+
+        for (method, path, allowed) in ROUTES {
+            let permission = match router.find(method, path).map(|route| route.access()) {
+                Ok(Access::Guarded(permission)) => permission,
+                _ => panic!("{} {} is not a guarded route", method, path),
+            };
+
+            for (role, allowed) in roles.iter().zip(allowed) {
+                assert_eq!(
+                    role.has_permission(permission),
+                    *allowed,
+                    "{:?} {} {}",
+                    role,
+                    method,
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_guarded_route_is_in_the_matrix() {
+        for (method, template, access) in router().declared_routes() {
+            if matches!(access, Access::Guarded(_)) {
+                assert!(
+                    ROUTES
+                        .iter()
+                        .any(|(m, path, _)| m == method && *path == template),
+                    "{} {}",
+                    method,
+                    template
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_admin_roles_can_log_in() {
+        assert!(UserRole::SuperAdmin.has_permission(Permission::AccessAdmin));
+        assert!(UserRole::Admin.has_permission(Permission::AccessAdmin));
+        assert!(!UserRole::User.has_permission(Permission::AccessAdmin));
+
+        let router = router();
+        let route = router.find(&Method::POST, "/api/admin/login").ok();
+        assert_eq!(route.map(|route| route.access()), Some(Access::Public));
+    }
+}
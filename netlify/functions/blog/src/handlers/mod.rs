@@ -1 +1 @@
-pub mod post_handler;
\ No newline at end of file
+pub mod post_handler;
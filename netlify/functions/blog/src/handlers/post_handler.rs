@@ -3,63 +3,34 @@ use lambda_runtime::Error;
 use mongodb::{bson::doc, Database};
 use serde_json::json;
 use shared_lib::{
-    models::post::Post, traits::model_traits::ModelTraits, AppErrorResponse, AppSuccessResponse,
-    DataInsertError,
+    error::AppError,
+    models::post::{Post, PostSummary},
+    traits::model_traits::ModelTraits,
+    AppSuccessResponse,
 };
 
 pub async fn add_post(
     database: &Database,
     new_post_data: Post,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let result = new_post_data.save(&database).await;
+    let result = new_post_data.save(database).await;
 
-    let _ = match result {
-        Ok(_insert_value) => {
-            return AppSuccessResponse::new(
-                StatusCode::OK,
-                Some("Data added successfully".to_string()),
-                None,
-            )
-        }
-        Err(error) => match error {
-            DataInsertError::FieldValidationError(error) => {
-                return AppErrorResponse::new(
-                    StatusCode::BAD_REQUEST,
-                    Some("An error occured".to_string()),
-                    Some(json!({
-                        "errors": error
-                    })),
-                )
-            }
-            DataInsertError::MongoDuplicateError(mut error) if error.code == 11000 => {
-                let duplicate_field_data =
-                    error.message.split("key: ").collect::<Vec<&str>>()[1].to_owned();
-
-                let duplicate_field_value =
-                    duplicate_field_data.split(" ").collect::<Vec<&str>>()[2].to_owned();
-
-                error.message =
-                    format!("An error occured. {} already exists", duplicate_field_value);
-
-                return AppErrorResponse::new(StatusCode::BAD_REQUEST, Some(error.message), None);
-            }
-            _ => {
-                return AppErrorResponse::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Some("An error occured".to_string()),
-                    None,
-                )
-            }
-        },
-    };
+    match result {
+        Ok(_insert_value) => AppSuccessResponse::new(
+            StatusCode::OK,
+            Some("Data added successfully".to_string()),
+            None,
+        ),
+        Err(error) => AppError::from(error).into_response(),
+    }
 }
 
 pub async fn get_posts(
     database: &Database,
     current_page: Option<i64>,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let post_response = Post::find_paginated(
-        &database,
+    let post_response = Post::find_paginated::<PostSummary>(
+        database,
         doc! {"is_published": true},
         Some(doc! {"title": true, "slug": true, "tags": true, "created_at": true, "_id": false}),
         Some(doc! { "created_at": -1 }),
@@ -85,11 +56,7 @@ pub async fn get_posts(
             )
         }
 
-        Err(_) => AppSuccessResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Some("An error occured fetching data".to_string()),
-            None,
-        ),
+        Err(error) => AppError::from(error).into_response(),
     }
 }
 
@@ -97,16 +64,16 @@ pub async fn get_post_by_slug(
     database: &Database,
     slug: String,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let post_response = Post::find_one(
-        &database,
+    let post_response = Post::find_one::<Post>(
+        database,
         doc! {"slug": slug, "is_published": true},
         Some(doc! {"_id": false}),
-        1,
     )
     .await;
 
     match post_response {
-        Ok(document) => AppSuccessResponse::new(
+        Ok(None) => AppError::NotFound("Post not found".to_string()).into_response(),
+        Ok(Some(document)) => AppSuccessResponse::new(
             StatusCode::OK,
             Some("Request successful".to_string()),
             Some(json!({
@@ -114,17 +81,13 @@ pub async fn get_post_by_slug(
             })),
         ),
 
-        Err(_) => AppSuccessResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Some("An error occured fetching data".to_string()),
-            None,
-        ),
+        Err(error) => AppError::from(error).into_response(),
     }
 }
 
 pub async fn get_featured_posts(database: &Database) -> Result<ApiGatewayProxyResponse, Error> {
-    let featured_post_response = Post::find(
-        &database,
+    let featured_post_response = Post::find::<PostSummary>(
+        database,
         doc! {"is_featured": true, "is_published": true},
         Some(doc! {"title": true, "slug": true, "tags": true, "updated_at": true, "_id": false}),
         Some(doc! { "updated_at": -1 }),
@@ -154,10 +117,6 @@ pub async fn get_featured_posts(database: &Database) -> Result<ApiGatewayProxyRe
             Some(serde_json::to_value(documents).unwrap_or_default()),
         ),
 
-        Err(_) => AppSuccessResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Some("An error occured fetching data".to_string()),
-            None,
-        ),
+        Err(error) => AppError::from(error).into_response(),
     }
 }
@@ -1,38 +1,18 @@
-use aws_lambda_events::{
-    apigw::ApiGatewayProxyResponse,
-    http::{Method, StatusCode},
-};
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use blog::handlers::post_handler::{get_featured_posts, get_post_by_slug, get_posts};
 use dotenvy::dotenv;
-use lambda_runtime::{service_fn, Error, LambdaEvent};
-use serde::de::Error as SerdeError;
-use serde::{Deserialize, Deserializer, Serialize};
+use lambda_runtime::{Error, LambdaEvent};
+use mongodb::Database;
+use serde::{Deserialize, Serialize};
 
 use shared_lib::{
-    database::client::connect_db, utils::cors::cors, AppErrorResponse, RequestPayload,
+    database::client::{connect_db, init_db},
+    layers::{CatchPanicLayer, CorsLayer, RequestIdLayer, TimingLayer},
+    router::{extract::Query, request_method, Router},
+    utils::deserialize::{from_str_to_bool, from_str_to_i64},
+    RequestPayload,
 };
-
-fn from_str_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: Result<String, _> = Deserialize::deserialize(deserializer);
-    match s {
-        Ok(s) => s.parse::<bool>().map_err(SerdeError::custom),
-        Err(_) => Ok(false), // default value
-    }
-}
-
-fn from_str_to_i64<'de, D>(deserializer: D) -> Result<i64, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: Result<String, _> = Deserialize::deserialize(deserializer);
-    match s {
-        Ok(s) => s.parse::<i64>().map_err(SerdeError::custom),
-        Err(_) => Ok(1), // default value
-    }
-}
+use tower::ServiceBuilder;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 struct RequestPostsQueryParams {
@@ -44,70 +24,54 @@ struct RequestPostsQueryParams {
     slug: String,
 }
 
-async fn handler(event: LambdaEvent<RequestPayload>) -> Result<ApiGatewayProxyResponse, Error> {
-    let database = connect_db().await?;
-
-    let http_method = event.payload.http_method.unwrap_or_default().to_uppercase();
-    let path = event.payload.path.unwrap_or_default();
-    let raw_query = event.payload.raw_query.unwrap_or_default();
-
-    if path != "/api/blog/posts" {
-        return AppErrorResponse::new(StatusCode::NOT_FOUND, Some("Not found".to_owned()), None);
-    }
-
-    if http_method == Method::OPTIONS.to_string() {
-        return cors();
-    }
-    // dbg!(event.payload.query_string_parameters.clone());
-    let request_post_query_params =
-        if let Some(query_params) = event.payload.query_string_parameters {
-            serde_json::from_value::<RequestPostsQueryParams>(query_params).unwrap_or_default()
-        } else {
-            RequestPostsQueryParams {
-                featured: false,
-                ..Default::default()
-            }
-        };
+fn router() -> Router<Database> {
+    Router::<Database>::new()
+        .get("/api/blog/posts", |request, database| async move {
+            let Query(request_post_query_params) =
+                request.extract::<Query<RequestPostsQueryParams>>()?;
+            let raw_query = request.event.payload.raw_query.unwrap_or_default();
 
-    // Get Posts
-    // let new_post = Post {
-    //     title: Some("Understanding the ? Operator in Rust-3".to_owned()),
-    //     slug: Some("understanding-the-question-mark-operator-in-rust-3".to_owned()),
-    //     rust_code_snippet: Some("fn main() {\n    println!(\"Hello World\");\n}".to_owned()),
-    //     content: Some("The From and Into traits are inherently linked, and this is actually part of its implementation. If you are able to convert type A from type B, then it should be easy to believe that we should be able to convert type B to type A.\n\nFrom\nThe From trait allows for a type to define how to create itself from another type, hence providing a very simple mechanism for converting between several types. There are numerous implementations of this trait within the standard library for conversion of primitive and common types.\n\nFor example we can easily convert a str into a String\n\n\nlet my_str = \"hello\";\nlet my_string = String::from(my_str);".to_owned()),
-    //     published_by: Some(vec![ObjectId::new()]),
-    //     tags: Some(vec!["rust".to_owned()]),
-    //     code_snippet_enabled: Some(true),
-    //     playground_enabled: Some(false),
-    //     is_published: Some(true),
-    //     is_featured: Some(false),
-    //     ..Default::default()
-    // };
-
-    let http_method_to_enum = Method::from_bytes(http_method.as_bytes()).unwrap_or_default();
-    // dbg!(&request_post_query_params);
-    match http_method_to_enum {
-        Method::GET => {
-            if request_post_query_params.featured == true {
+            if request_post_query_params.featured {
                 return get_featured_posts(&database).await;
             }
 
-            if request_post_query_params.featured == false && raw_query.contains("slug=") {
+            if raw_query.contains("slug=") {
                 return get_post_by_slug(&database, request_post_query_params.slug).await;
             }
 
-            return get_posts(&database, Some(request_post_query_params.current_page)).await;
-        }
-        _ => AppErrorResponse::new(
-            StatusCode::NOT_ACCEPTABLE,
-            Some("Not acceptable".to_owned()),
-            None,
-        ),
-    }
+            get_posts(&database, Some(request_post_query_params.current_page)).await
+        })
+        .get("/api/blog/posts/:slug", |request, database| async move {
+            let slug = request.path_param::<String>("slug").unwrap_or_default();
+
+            get_post_by_slug(&database, slug).await
+        })
+}
+
+async fn handler(event: LambdaEvent<RequestPayload>) -> Result<ApiGatewayProxyResponse, Error> {
+    let router = router();
+    let path = event.payload.path.clone().unwrap_or_default();
+
+    let route = match router.find(&request_method(&event), &path) {
+        Ok(route) => route,
+        Err(route_miss) => return route_miss.into_response(),
+    };
+
+    let database = connect_db().await?;
+
+    route.call(event, database).await
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     dotenv().unwrap_or_default();
-    lambda_runtime::run(service_fn(handler)).await
+    init_db().await?;
+    let service = ServiceBuilder::new()
+        .layer(RequestIdLayer)
+        .layer(TimingLayer)
+        .layer(CorsLayer)
+        .layer(CatchPanicLayer)
+        .service_fn(handler);
+
+    lambda_runtime::run(service).await
 }
@@ -0,0 +1,170 @@
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use chrono::{Duration, Utc};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    auth::{context::AuthContext, permission::Permission},
+    error::AppError,
+    models::{
+        api_key::{ApiKey, ApiKeySummary, API_KEY_PREFIX},
+        audit_event::{AuditAction, AuditEvent},
+    },
+    traits::model_traits::ModelTraits,
+    utils::token::{generate_identifier, generate_token, hash_token},
+    AppSuccessResponse,
+};
+use validator::Validate;
+
+use crate::{NewApiKeyData, RevokeApiKeyData};
+
+pub async fn get_api_keys(
+    database: &Database,
+    auth_context: &AuthContext,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let api_keys_response = ApiKey::find::<ApiKeySummary>(
+        database,
+        doc! {"user_id": auth_context.user_id},
+        Some(doc! {"secret_hash": false}),
+        Some(doc! { "created_at": -1 }),
+        100,
+    )
+    .await;
+
+    match api_keys_response {
+        Ok(api_keys) => AppSuccessResponse::new(
+            StatusCode::OK,
+            Some("Request successful".to_string()),
+            Some(json!({
+                "api_keys": api_keys
+            })),
+        ),
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+pub async fn create_api_key(
+    database: &Database,
+    auth_context: &AuthContext,
+    new_api_key_data: NewApiKeyData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    if let Err(errors) = new_api_key_data.validate() {
+        return AppError::from(errors).into_response();
+    }
+
+    let expires_at = match new_api_key_data.expires_in_days {
+        Some(days) => match Utc::now().checked_add_signed(Duration::days(days)) {
+            Some(expires_at) => Some(expires_at),
+            None => {
+                return AppError::BadRequest("Expiry is out of range".to_string()).into_response()
+            }
+        },
+        None => None,
+    };
+
+    let scopes = new_api_key_data.scopes.unwrap_or_default();
+
+    // Keys may only narrow the owner's permissions and can never mint further keys.
+    let has_forbidden_scope = scopes
+        .iter()
+        .any(|scope| *scope == Permission::ManageApiKeys || !auth_context.can(*scope));
+
+    if scopes.is_empty() || has_forbidden_scope {
+        return AppError::BadRequest(
+            "Scopes must be a non-empty subset of your own permissions".to_string(),
+        )
+        .into_response();
+    }
+
+    let prefix = generate_identifier(12);
+    let secret = generate_token();
+
+    let api_key = ApiKey {
+        user_id: Some(auth_context.user_id),
+        name: new_api_key_data.name,
+        prefix: Some(prefix.clone()),
+        secret_hash: Some(hash_token(&secret)),
+        scopes: Some(scopes),
+        expires_at,
+        ..Default::default()
+    };
+
+    match api_key.save(database).await {
+        Ok(insert_value) => {
+            AuditEvent {
+                action: Some(AuditAction::ApiKeyCreated),
+                target_type: Some("api_key".to_owned()),
+                target_id: insert_value
+                    .inserted_id
+                    .as_object_id()
+                    .map(|api_key_id| api_key_id.to_hex()),
+                after: Some(doc! {
+                    "name": api_key.name.clone(),
+                    "prefix": api_key.prefix.clone(),
+                    "scopes": to_bson(&api_key.scopes).unwrap_or_default(),
+                }),
+                ..AuditEvent::by(auth_context)
+            }
+            .record(database)
+            .await;
+
+            AppSuccessResponse::new(
+                StatusCode::OK,
+                Some("API key created. Copy it now, it will not be shown again".to_string()),
+                Some(json!({
+                    "api_key": {
+                        "key": format!("{}_{}_{}", API_KEY_PREFIX, prefix, secret),
+                        "name": api_key.name,
+                        "prefix": api_key.prefix,
+                        "scopes": api_key.scopes,
+                        "expires_at": api_key.expires_at,
+                    }
+                })),
+            )
+        }
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+pub async fn revoke_api_key(
+    database: &Database,
+    auth_context: &AuthContext,
+    revoke_api_key_data: RevokeApiKeyData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let api_key_id = match ObjectId::parse_str(revoke_api_key_data.api_key_id.unwrap_or_default()) {
+        Ok(api_key_id) => api_key_id,
+        Err(_) => {
+            return AppError::BadRequest("A valid API key id is required".to_string())
+                .into_response()
+        }
+    };
+
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    let update_result = ApiKey::update_one(
+        database,
+        doc! {"_id": api_key_id, "user_id": auth_context.user_id, "revoked_at": null},
+        doc! {"$set": {"revoked_at": now.clone(), "updated_at": now}},
+    )
+    .await;
+
+    match update_result {
+        Ok(update_result) if update_result.matched_count == 1 => {
+            AuditEvent {
+                action: Some(AuditAction::ApiKeyRevoked),
+                target_type: Some("api_key".to_owned()),
+                target_id: Some(api_key_id.to_hex()),
+                ..AuditEvent::by(auth_context)
+            }
+            .record(database)
+            .await;
+
+            AppSuccessResponse::new(StatusCode::OK, Some("API key revoked".to_string()), None)
+        }
+        Ok(_) => AppError::NotFound("API key not found".to_string()).into_response(),
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
@@ -0,0 +1,186 @@
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    encodings::Body,
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    error::AppError,
+    models::audit_event::{AuditAction, AuditEvent},
+    traits::model_traits::ModelTraits,
+    AppSuccessResponse,
+};
+
+use crate::AuditEventQueryParams;
+
+/// Upper bound on one page of the NDJSON export so it fits in one Lambda
+/// response. Longer exports continue from the `X-Next-Cursor` header.
+const NDJSON_EXPORT_LIMIT: i64 = 10_000;
+
+const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+pub async fn get_audit_events(
+    database: &Database,
+    audit_event_query_params: AuditEventQueryParams,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let filter = match audit_event_filter(&audit_event_query_params) {
+        Ok(filter) => filter,
+        Err(message) => return AppError::BadRequest(message).into_response(),
+    };
+
+    let current_page = audit_event_query_params
+        .current_page
+        .and_then(|current_page| current_page.parse::<i64>().ok());
+
+    let audit_events_response = AuditEvent::find_paginated::<AuditEvent>(
+        database,
+        filter,
+        None,
+        Some(doc! {"created_at": -1}),
+        current_page,
+        Some(50),
+    )
+    .await;
+
+    match audit_events_response {
+        Ok(paginated_audit_events_data) => AppSuccessResponse::new(
+            StatusCode::OK,
+            Some("Request successful".to_string()),
+            Some(json!({
+                "audit_events": paginated_audit_events_data.documents,
+                "metadata": {
+                    "pagination": paginated_audit_events_data.metadata
+                }
+            })),
+        ),
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+/// Streams the matching events as newline delimited JSON, oldest first, one
+/// page at a time. When more events match, the response carries the cursor
+/// for the next page in `X-Next-Cursor`.
+pub async fn export_audit_events(
+    database: &Database,
+    audit_event_query_params: AuditEventQueryParams,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let mut filter = match audit_event_filter(&audit_event_query_params) {
+        Ok(filter) => filter,
+        Err(message) => return AppError::BadRequest(message).into_response(),
+    };
+
+    if let Some(after) = audit_event_query_params.after.as_ref() {
+        match ObjectId::parse_str(after) {
+            Ok(after) => filter.insert("_id", doc! {"$gt": after}),
+            Err(_) => {
+                return AppError::BadRequest("A valid cursor is required".to_string())
+                    .into_response()
+            }
+        };
+    }
+
+    // Ids grow with insertion, so they order the events and make a stable cursor.
+    let mut audit_events = match AuditEvent::find::<AuditEvent>(
+        database,
+        filter,
+        None,
+        Some(doc! {"_id": 1}),
+        NDJSON_EXPORT_LIMIT + 1,
+    )
+    .await
+    {
+        Ok(audit_events) => audit_events,
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let has_more = audit_events.len() as i64 > NDJSON_EXPORT_LIMIT;
+    audit_events.truncate(NDJSON_EXPORT_LIMIT as usize);
+
+    let ndjson = match audit_events
+        .iter()
+        .map(|audit_event| serde_json::to_string(audit_event).map(|line| line + "\n"))
+        .collect::<Result<String, _>>()
+    {
+        Ok(ndjson) => ndjson,
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let mut response = AppSuccessResponse::new(StatusCode::OK, None, None)?;
+    let content_type = HeaderValue::from_static("application/x-ndjson");
+
+    response.headers.insert(CONTENT_TYPE, content_type.clone());
+    response
+        .multi_value_headers
+        .insert(CONTENT_TYPE, content_type);
+
+    let next_cursor = audit_events
+        .last()
+        .and_then(|audit_event| audit_event.id)
+        .filter(|_| has_more);
+
+    if let Some(next_cursor) = next_cursor {
+        let next_cursor = HeaderValue::from_str(&next_cursor.to_hex())?;
+
+        response
+            .headers
+            .insert(NEXT_CURSOR_HEADER, next_cursor.clone());
+        response
+            .multi_value_headers
+            .insert(NEXT_CURSOR_HEADER, next_cursor);
+    }
+
+    response.body = Some(Body::Text(ndjson));
+
+    Ok(response)
+}
+
+fn audit_event_filter(
+    audit_event_query_params: &AuditEventQueryParams,
+) -> Result<Document, String> {
+    let mut filter = doc! {};
+
+    if let Some(actor_id) = audit_event_query_params.actor_id.as_ref() {
+        let actor_id =
+            ObjectId::parse_str(actor_id).map_err(|_| "A valid actor id is required".to_owned())?;
+        filter.insert("actor_id", actor_id);
+    }
+
+    if let Some(action) = audit_event_query_params.action.as_ref() {
+        serde_json::from_value::<AuditAction>(json!(action))
+            .map_err(|_| format!("Unknown audit action {}", action))?;
+        filter.insert("action", action);
+    }
+
+    let mut created_at = doc! {};
+
+    if let Some(from) = audit_event_query_params.from.as_ref() {
+        created_at.insert("$gte", date_bound(from)?);
+    }
+
+    if let Some(to) = audit_event_query_params.to.as_ref() {
+        created_at.insert("$lt", date_bound(to)?);
+    }
+
+    if !created_at.is_empty() {
+        filter.insert("created_at", created_at);
+    }
+
+    Ok(filter)
+}
+
+/// Dates are stored as RFC 3339 strings. Writing the bound with all nine
+/// fractional digits keeps the string comparison in the same order as time.
+fn date_bound(date: &str) -> Result<String, String> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| {
+            date.with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Nanos, true)
+        })
+        .map_err(|_| format!("{} is not a valid RFC 3339 date", date))
+}
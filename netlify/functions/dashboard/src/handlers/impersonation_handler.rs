@@ -0,0 +1,215 @@
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    http::{header::SET_COOKIE, HeaderValue, StatusCode},
+};
+use chrono::{Duration, Utc};
+use cookie::Cookie;
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    auth::{
+        context::AuthContext,
+        impersonation::{
+            impersonation_cookie, impersonation_cookie_name, impersonation_ttl_minutes,
+        },
+    },
+    error::AppError,
+    models::{
+        audit_event::{AuditAction, AuditEvent},
+        impersonation_session::ImpersonationSession,
+        user::{User, UserRole},
+    },
+    traits::model_traits::ModelTraits,
+    utils::cookie::CookieKeys,
+    AppSuccessResponse,
+};
+
+use crate::StartImpersonationData;
+
+pub async fn start_impersonation(
+    database: &Database,
+    auth_context: &AuthContext,
+    start_impersonation_data: StartImpersonationData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user_id = match ObjectId::parse_str(start_impersonation_data.user_id.unwrap_or_default()) {
+        Ok(user_id) if user_id != auth_context.user_id => user_id,
+        _ => {
+            return AppError::BadRequest("A valid id of another user is required".to_string())
+                .into_response()
+        }
+    };
+
+    let user = User::find::<User>(database, doc! {"_id": user_id}, None, None, 1)
+        .await?
+        .into_iter()
+        .next();
+
+    let user = match user {
+        Some(user) if user.is_active() => user,
+        _ => return AppError::NotFound("User not found".to_string()).into_response(),
+    };
+
+    if user.role == Some(UserRole::SuperAdmin) {
+        return AppError::Forbidden("Super admins cannot be impersonated".to_string())
+            .into_response();
+    }
+
+    let allow_destructive = start_impersonation_data
+        .allow_destructive
+        .unwrap_or_default();
+    let expires_at = Utc::now() + Duration::minutes(impersonation_ttl_minutes());
+
+    let impersonation_session = ImpersonationSession {
+        impersonator_id: Some(auth_context.user_id),
+        user_id: Some(user_id),
+        allow_destructive: Some(allow_destructive),
+        reason: start_impersonation_data.reason,
+        expires_at: Some(expires_at),
+        ..Default::default()
+    };
+
+    let session_id = match impersonation_session.save(database).await {
+        Ok(insert_value) => insert_value.inserted_id.as_object_id(),
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let session_id = match session_id {
+        Some(session_id) => session_id,
+        None => {
+            return AppError::Internal("Impersonation session id is not an ObjectId".to_owned())
+                .into_response()
+        }
+    };
+
+    AuditEvent {
+        action: Some(AuditAction::ImpersonationStarted),
+        target_type: Some("user".to_owned()),
+        target_id: Some(user_id.to_hex()),
+        after: Some(doc! {
+            "session_id": session_id,
+            "allow_destructive": allow_destructive,
+            "reason": impersonation_session.reason.clone(),
+        }),
+        ..AuditEvent::by(auth_context)
+    }
+    .record(database)
+    .await;
+
+    let cookie_value = CookieKeys::from_env()?.encrypt(impersonation_cookie(&session_id));
+
+    let mut response = AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Impersonation started".to_string()),
+        Some(json!({
+            "impersonation": {
+                "user_id": user_id.to_hex(),
+                "username": user.username,
+                "role": user.role,
+                "allow_destructive": allow_destructive,
+                "expires_at": expires_at,
+            }
+        })),
+    )?;
+
+    response
+        .headers
+        .insert(SET_COOKIE, HeaderValue::from_str(&cookie_value)?);
+
+    Ok(response)
+}
+
+/// Ends the impersonation session in the cookie. Only the super admin who
+/// started it, identified by their own session cookie, can end it.
+pub async fn stop_impersonation(
+    database: &Database,
+    username: Option<String>,
+    session_id: Option<ObjectId>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let impersonator = User::find::<User>(
+        database,
+        doc! {"username": username.unwrap_or_default()},
+        None,
+        None,
+        1,
+    )
+    .await?
+    .into_iter()
+    .next();
+
+    let (impersonator, session_id) = match (impersonator, session_id) {
+        (Some(impersonator), Some(session_id)) => (impersonator, session_id),
+        _ => return no_impersonation_response(),
+    };
+
+    let impersonation_session = ImpersonationSession::find::<ImpersonationSession>(
+        database,
+        doc! {"_id": session_id, "impersonator_id": impersonator.id, "ended_at": null},
+        None,
+        None,
+        1,
+    )
+    .await?
+    .into_iter()
+    .next();
+
+    let impersonation_session = match impersonation_session {
+        Some(impersonation_session) => impersonation_session,
+        None => return no_impersonation_response(),
+    };
+
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    ImpersonationSession::update_one(
+        database,
+        doc! {"_id": session_id},
+        doc! {"$set": {"ended_at": now.clone(), "updated_at": now}},
+    )
+    .await?;
+
+    AuditEvent {
+        actor_id: impersonator.id,
+        action: Some(AuditAction::ImpersonationEnded),
+        target_type: Some("user".to_owned()),
+        target_id: impersonation_session
+            .user_id
+            .map(|user_id| user_id.to_hex()),
+        after: Some(doc! {"session_id": session_id}),
+        ..Default::default()
+    }
+    .record(database)
+    .await;
+
+    let mut response = AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Impersonation ended".to_string()),
+        None,
+    )?;
+
+    response
+        .headers
+        .insert(SET_COOKIE, remove_impersonation_cookie()?);
+
+    Ok(response)
+}
+
+fn no_impersonation_response() -> Result<ApiGatewayProxyResponse, Error> {
+    let mut response = AppError::NotFound("No active impersonation".to_string()).into_response()?;
+
+    response
+        .headers
+        .insert(SET_COOKIE, remove_impersonation_cookie()?);
+
+    Ok(response)
+}
+
+fn remove_impersonation_cookie() -> Result<HeaderValue, Error> {
+    let mut cookie = Cookie::new(impersonation_cookie_name(), "");
+    cookie.set_path("/");
+    cookie.make_removal();
+
+    Ok(HeaderValue::from_str(&cookie.to_string())?)
+}
@@ -0,0 +1,162 @@
+use std::env;
+
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use chrono::{Duration, Utc};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, to_bson},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    auth::context::AuthContext,
+    error::AppError,
+    mailer::{EmailMessage, MailTransport, Mailer},
+    models::{
+        audit_event::{AuditAction, AuditEvent},
+        invitation::{Invitation, PendingInvitation},
+        user::User,
+    },
+    traits::model_traits::ModelTraits,
+    utils::token::{generate_token, hash_token},
+    AppSuccessResponse,
+};
+
+use crate::NewInvitationData;
+
+pub async fn create_invitation(
+    database: &Database,
+    auth_context: &AuthContext,
+    new_invitation_data: NewInvitationData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let inviter_role = auth_context.role.clone();
+    let invited_role = new_invitation_data.role.clone().unwrap_or_default();
+
+    if !inviter_role.can_assign_role(&invited_role) {
+        return AppError::Forbidden(
+            "You are not allowed to invite users with this role".to_string(),
+        )
+        .into_response();
+    }
+
+    let email = new_invitation_data.email.clone().unwrap_or_default();
+
+    match User::count_documents(database, doc! {"email": &email}).await {
+        Ok(0) => (),
+        Ok(_) => {
+            return AppError::BadRequest(format!("An error occured. {} already exists", email))
+                .into_response()
+        }
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let ttl_hours = env::var("INVITATION_TTL_HOURS")
+        .unwrap_or_default()
+        .parse::<i64>()
+        .unwrap_or(72);
+
+    let token = generate_token();
+
+    let invitation = Invitation {
+        email: new_invitation_data.email,
+        role: Some(invited_role),
+        token_hash: Some(hash_token(&token)),
+        invited_by: Some(auth_context.user_id),
+        expires_at: Some(Utc::now() + Duration::hours(ttl_hours)),
+        ..Default::default()
+    };
+
+    match invitation.save(database).await {
+        Ok(insert_value) => {
+            AuditEvent {
+                action: Some(AuditAction::InvitationCreated),
+                target_type: Some("invitation".to_owned()),
+                target_id: insert_value
+                    .inserted_id
+                    .as_object_id()
+                    .map(|invitation_id| invitation_id.to_hex()),
+                after: Some(doc! {
+                    "email": invitation.email.clone(),
+                    "role": to_bson(&invitation.role).unwrap_or_default(),
+                }),
+                ..AuditEvent::by(auth_context)
+            }
+            .record(database)
+            .await;
+        }
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let frontend_base_url = env::var("FRONTEND_BASE_URL").unwrap_or_default();
+    let invitation_link = format!("{}/accept-invitation?token={}", frontend_base_url, token);
+
+    let message = EmailMessage::new(
+        email,
+        "You have been invited".to_owned(),
+        format!(
+            "You have been invited to join as {}. Open the link below to choose a username and password. The link expires in {} hours.\n\n{}",
+            serde_json::to_value(&invitation.role)
+                .unwrap_or_default()
+                .as_str()
+                .unwrap_or_default(),
+            ttl_hours,
+            invitation_link
+        ),
+    );
+
+    if let Err(error) = Mailer::from_env().send(&message).await {
+        eprintln!("Failed to send invitation email: {}", error);
+
+        return AppError::EmailNotSent(
+            "Invitation created but the email could not be sent".to_string(),
+        )
+        .into_response();
+    }
+
+    AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Invitation sent".to_string()),
+        Some(json!({
+            "invitation": {
+                "email": invitation.email,
+                "role": invitation.role,
+                "expires_at": invitation.expires_at,
+            }
+        })),
+    )
+}
+
+pub async fn get_pending_invitations(
+    database: &Database,
+    current_page: Option<i64>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let invitations_response = Invitation::find_paginated::<PendingInvitation>(
+        database,
+        doc! {"redeemed_at": null},
+        Some(doc! {"email": true, "role": true, "expires_at": true, "created_at": true}),
+        Some(doc! { "created_at": -1 }),
+        current_page,
+        Some(20),
+    )
+    .await;
+
+    match invitations_response {
+        Ok(paginated_invitations_data) => {
+            let invitations = paginated_invitations_data.documents;
+            let pagination_metadata = paginated_invitations_data.metadata;
+
+            AppSuccessResponse::new(
+                StatusCode::OK,
+                Some("Request successful".to_string()),
+                Some(json!({
+                    "invitations": invitations,
+                    "metadata": {
+                        "pagination": pagination_metadata
+                    }
+                })),
+            )
+        }
+
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
@@ -1 +1,6 @@
-pub mod dashboard_handler;
\ No newline at end of file
+pub mod api_key_handler;
+pub mod audit_handler;
+pub mod dashboard_handler;
+pub mod impersonation_handler;
+pub mod invitation_handler;
+pub mod user_management_handler;
@@ -0,0 +1,392 @@
+use std::env;
+
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use chrono::{Duration, Utc};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    auth::context::AuthContext,
+    error::AppError,
+    mailer::{EmailMessage, MailTransport, Mailer},
+    models::{
+        audit_event::{AuditAction, AuditEvent},
+        post::Post,
+        user::{User, UserProfile, UserRole},
+    },
+    traits::model_traits::ModelTraits,
+    utils::token::{generate_token, hash_token},
+    AppSuccessResponse,
+};
+
+use crate::ManageUserData;
+
+pub async fn get_users(
+    database: &Database,
+    current_page: Option<i64>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let users_response = User::find_paginated::<UserProfile>(
+        database,
+        doc! {},
+        Some(User::safe_projection()),
+        Some(doc! { "created_at": -1 }),
+        current_page,
+        Some(20),
+    )
+    .await;
+
+    match users_response {
+        Ok(paginated_users_data) => {
+            let users = paginated_users_data.documents;
+            let pagination_metadata = paginated_users_data.metadata;
+
+            AppSuccessResponse::new(
+                StatusCode::OK,
+                Some("Request successful".to_string()),
+                Some(json!({
+                    "users": users,
+                    "metadata": {
+                        "pagination": pagination_metadata
+                    }
+                })),
+            )
+        }
+
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+pub async fn change_user_role(
+    database: &Database,
+    auth_context: &AuthContext,
+    manage_user_data: ManageUserData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user = match find_target_user(database, &manage_user_data.user_id).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let new_role = match manage_user_data.role {
+        Some(role) => role,
+        None => return AppError::BadRequest("Role is required".to_string()).into_response(),
+    };
+
+    let removes_super_admin = new_role != UserRole::SuperAdmin && is_active_super_admin(&user);
+
+    let audit_event = AuditEvent {
+        before: Some(doc! {"role": to_bson(&user.role).unwrap_or_default()}),
+        after: Some(doc! {"role": to_bson(&new_role).unwrap_or_default()}),
+        ..user_audit_event(auth_context, &user, AuditAction::RoleChanged)
+    };
+
+    update_user(
+        database,
+        &user,
+        doc! {"role": to_bson(&new_role).unwrap_or_default()},
+        removes_super_admin,
+        "User role updated",
+        audit_event,
+    )
+    .await
+}
+
+pub async fn set_user_active(
+    database: &Database,
+    auth_context: &AuthContext,
+    manage_user_data: ManageUserData,
+    is_active: bool,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user = match find_target_user(database, &manage_user_data.user_id).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let removes_super_admin = !is_active && is_active_super_admin(&user);
+
+    let (message, action) = if is_active {
+        ("User reactivated", AuditAction::UserReactivated)
+    } else {
+        ("User deactivated", AuditAction::UserDeactivated)
+    };
+
+    let audit_event = AuditEvent {
+        before: Some(doc! {"is_active": user.is_active()}),
+        after: Some(doc! {"is_active": is_active}),
+        ..user_audit_event(auth_context, &user, action)
+    };
+
+    update_user(
+        database,
+        &user,
+        doc! {"is_active": is_active},
+        removes_super_admin,
+        message,
+        audit_event,
+    )
+    .await
+}
+
+pub async fn force_password_reset(
+    database: &Database,
+    auth_context: &AuthContext,
+    manage_user_data: ManageUserData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user = match find_target_user(database, &manage_user_data.user_id).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let ttl_hours = env::var("PASSWORD_RESET_TTL_HOURS")
+        .unwrap_or_default()
+        .parse::<i64>()
+        .unwrap_or(24);
+
+    let token = generate_token();
+    let expires_at = Utc::now() + Duration::hours(ttl_hours);
+
+    let response = update_user(
+        database,
+        &user,
+        doc! {
+            "password_reset_required": true,
+            "password_reset_token_hash": hash_token(&token),
+            "password_reset_expires_at": to_bson(&expires_at).unwrap_or_default(),
+        },
+        false,
+        "Password reset required",
+        user_audit_event(auth_context, &user, AuditAction::PasswordResetForced),
+    )
+    .await?;
+
+    let frontend_base_url = env::var("FRONTEND_BASE_URL").unwrap_or_default();
+    let reset_link = format!("{}/reset-password?token={}", frontend_base_url, token);
+
+    let message = EmailMessage::new(
+        user.email.clone().unwrap_or_default(),
+        "Reset your password".to_owned(),
+        format!(
+            "An administrator requires you to choose a new password before logging in again. The link expires in {} hours.\n\n{}",
+            ttl_hours, reset_link
+        ),
+    );
+
+    if let Err(error) = Mailer::from_env().send(&message).await {
+        eprintln!("Failed to send password reset email: {}", error);
+    }
+
+    Ok(response)
+}
+
+pub async fn delete_user(
+    database: &Database,
+    auth_context: &AuthContext,
+    manage_user_data: ManageUserData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user = match find_target_user(database, &manage_user_data.user_id).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let new_author = match find_target_user(database, &manage_user_data.reassign_posts_to).await {
+        Ok(new_author) if new_author.id == user.id => {
+            return AppError::BadRequest("Posts must be reassigned to a different user".to_string())
+                .into_response()
+        }
+        Ok(new_author) if !new_author.is_active() || new_author.erased_at.is_some() => {
+            return AppError::BadRequest("Posts must be reassigned to an active user".to_string())
+                .into_response()
+        }
+        Ok(new_author) => new_author,
+        Err(response) => return Ok(response),
+    };
+
+    // Deactivate first, so the last super admin check holds against a
+    // concurrent demotion before anything is deleted.
+    if is_active_super_admin(&user)
+        && !remove_from_super_admins(database, &user, doc! {"is_active": false}).await?
+    {
+        return last_super_admin_response();
+    }
+
+    Post::update_many(
+        database,
+        doc! {"published_by": user.id},
+        doc! {"$addToSet": {"published_by": new_author.id}},
+    )
+    .await?;
+
+    Post::update_many(
+        database,
+        doc! {"published_by": user.id},
+        doc! {"$pull": {"published_by": user.id}},
+    )
+    .await?;
+
+    User::delete_one(database, doc! {"_id": user.id}).await?;
+
+    AuditEvent {
+        before: Some(doc! {
+            "username": user.username.clone(),
+            "email": user.email.clone(),
+            "role": to_bson(&user.role).unwrap_or_default(),
+        }),
+        after: Some(doc! {"posts_reassigned_to": new_author.id}),
+        ..user_audit_event(auth_context, &user, AuditAction::UserDeleted)
+    }
+    .record(database)
+    .await;
+
+    AppSuccessResponse::new(StatusCode::OK, Some("User deleted".to_string()), None)
+}
+
+/// Hard deletes erased accounts whose grace period has passed. Their posts
+/// were already handed to the placeholder author when they were erased.
+pub async fn purge_erased_users(
+    database: &Database,
+    auth_context: &AuthContext,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let erased_users = User::find::<User>(
+        database,
+        doc! {"erased_at": {"$ne": null}},
+        Some(User::safe_projection()),
+        None,
+        1000,
+    )
+    .await?;
+
+    let mut purged_count = 0;
+
+    for user in erased_users.into_iter().filter(User::is_due_for_purge) {
+        User::delete_one(database, doc! {"_id": user.id}).await?;
+
+        AuditEvent {
+            before: Some(doc! {"erased_at": to_bson(&user.erased_at).unwrap_or_default()}),
+            ..user_audit_event(auth_context, &user, AuditAction::UserDeleted)
+        }
+        .record(database)
+        .await;
+
+        purged_count += 1;
+    }
+
+    AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Erased users purged".to_string()),
+        Some(json!({
+            "purged_count": purged_count
+        })),
+    )
+}
+
+async fn find_target_user(
+    database: &Database,
+    user_id: &Option<String>,
+) -> Result<User, ApiGatewayProxyResponse> {
+    let user_id = match ObjectId::parse_str(user_id.clone().unwrap_or_default()) {
+        Ok(user_id) => user_id,
+        Err(_) => {
+            return Err(
+                AppError::BadRequest("A valid user id is required".to_string())
+                    .into_response()
+                    .unwrap_or_default(),
+            )
+        }
+    };
+
+    let user = match User::find::<User>(database, doc! {"_id": user_id}, None, None, 1).await {
+        Ok(users) => users.into_iter().next(),
+        Err(error) => return Err(AppError::from(error).into_response().unwrap_or_default()),
+    };
+
+    match user {
+        Some(user) => Ok(user),
+        None => Err(AppError::NotFound("User not found".to_string())
+            .into_response()
+            .unwrap_or_default()),
+    }
+}
+
+fn is_active_super_admin(user: &User) -> bool {
+    user.role == Some(UserRole::SuperAdmin) && user.is_active()
+}
+
+/// Applies `changes`, which take `user` out of the active super admins, and
+/// undoes them if no active super admin is left. Counting after the write
+/// instead of before means two concurrent removals cannot both pass on a
+/// count that still includes the other one. Returns whether the change stuck.
+async fn remove_from_super_admins(
+    database: &Database,
+    user: &User,
+    changes: mongodb::bson::Document,
+) -> mongodb::error::Result<bool> {
+    User::update_one(database, doc! {"_id": user.id}, doc! {"$set": changes}).await?;
+
+    let active_super_admins_count = User::count_documents(
+        database,
+        doc! {"role": "super_admin", "is_active": {"$ne": false}},
+    )
+    .await?;
+
+    if active_super_admins_count > 0 {
+        return Ok(true);
+    }
+
+    User::update_one(
+        database,
+        doc! {"_id": user.id},
+        doc! {"$set": {
+            "role": to_bson(&user.role).unwrap_or_default(),
+            "is_active": user.is_active(),
+        }},
+    )
+    .await?;
+
+    Ok(false)
+}
+
+fn last_super_admin_response() -> Result<ApiGatewayProxyResponse, Error> {
+    AppError::Conflict("The last super admin cannot be removed".to_string()).into_response()
+}
+
+fn user_audit_event(auth_context: &AuthContext, user: &User, action: AuditAction) -> AuditEvent {
+    AuditEvent {
+        action: Some(action),
+        target_type: Some("user".to_owned()),
+        target_id: user.id.map(|user_id| user_id.to_hex()),
+        ..AuditEvent::by(auth_context)
+    }
+}
+
+/// Applies `changes` to `user` and records `audit_event` once they are saved.
+/// Set `removes_super_admin` when the changes demote or deactivate an active
+/// super admin, so the last one is kept.
+async fn update_user(
+    database: &Database,
+    user: &User,
+    mut changes: mongodb::bson::Document,
+    removes_super_admin: bool,
+    message: &str,
+    audit_event: AuditEvent,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    changes.insert("updated_at", to_bson(&Utc::now()).unwrap_or_default());
+
+    let update_result = match removes_super_admin {
+        true => remove_from_super_admins(database, user, changes).await,
+        false => User::update_one(database, doc! {"_id": user.id}, doc! {"$set": changes})
+            .await
+            .map(|_| true),
+    };
+
+    match update_result {
+        Ok(false) => last_super_admin_response(),
+        Ok(true) => {
+            audit_event.record(database).await;
+
+            AppSuccessResponse::new(StatusCode::OK, Some(message.to_string()), None)
+        }
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
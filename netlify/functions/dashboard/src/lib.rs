@@ -1,9 +1,30 @@
-use chrono::{DateTime, Utc};
-use mongodb::bson::{oid::ObjectId, Document};
 use serde::{Deserialize, Serialize};
+use shared_lib::{
+    auth::{csrf::csrf_token_response, impersonation::ImpersonationCookie, permission::Permission},
+    models::{post::RecentPost, user::UserRole},
+    router::{
+        context::RequestContext,
+        extract::{Cookie, Json, Query},
+        Router,
+    },
+    utils::{cookie::SessionCookie, deserialize::from_str_to_i64},
+};
+use validator::Validate;
 
 pub mod handlers;
 
+use crate::handlers::{
+    api_key_handler::{create_api_key, get_api_keys, revoke_api_key},
+    audit_handler::{export_audit_events, get_audit_events},
+    dashboard_handler::get_metadata,
+    impersonation_handler::{start_impersonation, stop_impersonation},
+    invitation_handler::{create_invitation, get_pending_invitations},
+    user_management_handler::{
+        change_user_role, delete_user, force_password_reset, get_users, purge_erased_users,
+        set_user_active,
+    },
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct UserLoginData {
     pub username: Option<String>,
@@ -16,5 +37,378 @@ pub struct DashboardMetadata {
     pub published_posts_count: u64,
     pub draft_posts_count: u64,
     pub featured_posts_count: u64,
-    pub recent_posts: Vec<Document>,
+    pub recent_posts: Vec<RecentPost>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NewInvitationData {
+    pub email: Option<String>,
+    pub role: Option<UserRole>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ManageUserData {
+    pub user_id: Option<String>,
+    pub role: Option<UserRole>,
+    pub reassign_posts_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Default)]
+pub struct NewApiKeyData {
+    pub name: Option<String>,
+    pub scopes: Option<Vec<Permission>>,
+    #[validate(range(
+        min = 1,
+        max = 3650,
+        message = "Expiry must be between 1 and 3650 days"
+    ))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RevokeApiKeyData {
+    pub api_key_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AuditEventQueryParams {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub current_page: Option<String>,
+    /// Export only: continue after the event with this id, taken from the
+    /// previous page's `X-Next-Cursor` header.
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StartImpersonationData {
+    pub user_id: Option<String>,
+    pub allow_destructive: Option<bool>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RequestDashboardQueryParams {
+    #[serde(default, deserialize_with = "from_str_to_i64")]
+    pub current_page: i64,
+}
+
+/// Every dashboard route, each guarded by the permission it requires.
+pub fn router() -> Router<RequestContext> {
+    Router::<RequestContext>::new()
+        .get("/api/dashboard/csrf-token", |_, _| async {
+            csrf_token_response()
+        })
+        // Not behind the guard, which would resolve to the impersonated user.
+        .post(
+            "/api/dashboard/impersonation/stop",
+            |request, context| async move {
+                let session_cookie = request.extract::<Option<Cookie<SessionCookie>>>()?;
+                let impersonation_cookie =
+                    request.extract::<Option<Cookie<ImpersonationCookie, _>>>()?;
+
+                stop_impersonation(
+                    &context.database,
+                    session_cookie.map(|Cookie(username, _)| username),
+                    impersonation_cookie.map(|Cookie(session_id, _)| session_id),
+                )
+                .await
+            },
+        )
+        .get_guarded(
+            "/api/dashboard/metadata",
+            Permission::ViewDashboard,
+            |_, context, _| async move { get_metadata(&context.database).await },
+        )
+        .get_guarded(
+            "/api/dashboard/invitations",
+            Permission::ManageInvitations,
+            |request, context, _| async move {
+                let Query(request_dashboard_query_params) =
+                    request.extract::<Query<RequestDashboardQueryParams>>()?;
+
+                get_pending_invitations(
+                    &context.database,
+                    Some(request_dashboard_query_params.current_page),
+                )
+                .await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/invitations",
+            Permission::ManageInvitations,
+            |request, context, auth_context| async move {
+                let Json(new_invitation_data) = request.extract::<Json<NewInvitationData>>()?;
+
+                create_invitation(&context.database, &auth_context, new_invitation_data).await
+            },
+        )
+        .get_guarded(
+            "/api/dashboard/users",
+            Permission::ManageUsers,
+            |request, context, _| async move {
+                let Query(request_dashboard_query_params) =
+                    request.extract::<Query<RequestDashboardQueryParams>>()?;
+
+                get_users(
+                    &context.database,
+                    Some(request_dashboard_query_params.current_page),
+                )
+                .await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/users/role",
+            Permission::ManageUsers,
+            |request, context, auth_context| async move {
+                let Json(manage_user_data) = request.extract::<Json<ManageUserData>>()?;
+
+                change_user_role(&context.database, &auth_context, manage_user_data).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/users/deactivate",
+            Permission::ManageUsers,
+            |request, context, auth_context| async move {
+                let Json(manage_user_data) = request.extract::<Json<ManageUserData>>()?;
+
+                set_user_active(&context.database, &auth_context, manage_user_data, false).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/users/reactivate",
+            Permission::ManageUsers,
+            |request, context, auth_context| async move {
+                let Json(manage_user_data) = request.extract::<Json<ManageUserData>>()?;
+
+                set_user_active(&context.database, &auth_context, manage_user_data, true).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/users/force-password-reset",
+            Permission::ManageUsers,
+            |request, context, auth_context| async move {
+                let Json(manage_user_data) = request.extract::<Json<ManageUserData>>()?;
+
+                force_password_reset(&context.database, &auth_context, manage_user_data).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/users/delete",
+            Permission::ManageUsers,
+            |request, context, auth_context| async move {
+                let Json(manage_user_data) = request.extract::<Json<ManageUserData>>()?;
+
+                delete_user(&context.database, &auth_context, manage_user_data).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/users/purge-erased",
+            Permission::ManageUsers,
+            |_, context, auth_context| async move {
+                purge_erased_users(&context.database, &auth_context).await
+            },
+        )
+        .get_guarded(
+            "/api/dashboard/api-keys",
+            Permission::ManageApiKeys,
+            |_, context, auth_context| async move {
+                get_api_keys(&context.database, &auth_context).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/api-keys",
+            Permission::ManageApiKeys,
+            |request, context, auth_context| async move {
+                let Json(new_api_key_data) = request.extract::<Json<NewApiKeyData>>()?;
+
+                create_api_key(&context.database, &auth_context, new_api_key_data).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/api-keys/revoke",
+            Permission::ManageApiKeys,
+            |request, context, auth_context| async move {
+                let Json(revoke_api_key_data) = request.extract::<Json<RevokeApiKeyData>>()?;
+
+                revoke_api_key(&context.database, &auth_context, revoke_api_key_data).await
+            },
+        )
+        .post_guarded(
+            "/api/dashboard/impersonation/start",
+            Permission::ImpersonateUsers,
+            |request, context, auth_context| async move {
+                let Json(start_impersonation_data) =
+                    request.extract::<Json<StartImpersonationData>>()?;
+
+                start_impersonation(&context.database, &auth_context, start_impersonation_data)
+                    .await
+            },
+        )
+        .get_guarded(
+            "/api/dashboard/audit-events",
+            Permission::ViewAuditLog,
+            |request, context, _| async move {
+                let Query(audit_event_query_params) =
+                    request.extract::<Query<AuditEventQueryParams>>()?;
+
+                get_audit_events(&context.database, audit_event_query_params).await
+            },
+        )
+        .get_guarded(
+            "/api/dashboard/audit-events/export",
+            Permission::ViewAuditLog,
+            |request, context, _| async move {
+                let Query(audit_event_query_params) =
+                    request.extract::<Query<AuditEventQueryParams>>()?;
+
+                export_audit_events(&context.database, audit_event_query_params).await
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::http::Method;
+    use shared_lib::router::{Access, RouteMiss};
+
+    use super::*;
+
+    const ROUTES: &[(Method, &str, [bool; 3])] = &[
+        // allowed for [super_admin, admin, user]
+        (Method::GET, "/api/dashboard/metadata", [true, true, false]),
+        (
+            Method::GET,
+            "/api/dashboard/invitations",
+            [true, true, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/invitations",
+            [true, true, false],
+        ),
+        (Method::GET, "/api/dashboard/users", [true, false, false]),
+        (
+            Method::POST,
+            "/api/dashboard/users/role",
+            [true, false, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/users/deactivate",
+            [true, false, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/users/reactivate",
+            [true, false, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/users/force-password-reset",
+            [true, false, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/users/delete",
+            [true, false, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/users/purge-erased",
+            [true, false, false],
+        ),
+        (Method::GET, "/api/dashboard/api-keys", [true, true, false]),
+        (Method::POST, "/api/dashboard/api-keys", [true, true, false]),
+        (
+            Method::POST,
+            "/api/dashboard/api-keys/revoke",
+            [true, true, false],
+        ),
+        (
+            Method::POST,
+            "/api/dashboard/impersonation/start",
+            [true, false, false],
+        ),
+        (
+            Method::GET,
+            "/api/dashboard/audit-events",
+            [true, false, false],
+        ),
+        (
+            Method::GET,
+            "/api/dashboard/audit-events/export",
+            [true, false, false],
+        ),
+    ];
+
+    #[test]
+    fn every_role_and_route_is_authorized_as_expected() {
+        let router = router();
+        let roles = [UserRole::SuperAdmin, UserRole::Admin, UserRole::User];
+
+        for (method, path, allowed) in ROUTES {
+            let permission = match router.find(method, path).map(|route| route.access()) {
+                Ok(Access::Guarded(permission)) => permission,
+                _ => panic!("{} {} is not a guarded route", method, path),
+            };
+
+            for (role, allowed) in roles.iter().zip(allowed) {
+                assert_eq!(
+                    role.has_permission(permission),
+                    *allowed,
+                    "{:?} {} {}",
+                    role,
+                    method,
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_guarded_route_is_in_the_matrix() {
+        for (method, template, access) in router().declared_routes() {
+            if matches!(access, Access::Guarded(_)) {
+                assert!(
+                    ROUTES
+                        .iter()
+                        .any(|(m, path, _)| m == method && *path == template),
+                    "{} {}",
+                    method,
+                    template
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_routes_are_not_matched() {
+        let router = router();
+
+        assert!(matches!(
+            router.find(&Method::DELETE, "/api/dashboard/metadata"),
+            Err(RouteMiss::MethodNotAllowed(_))
+        ));
+        assert!(matches!(
+            router.find(&Method::GET, "/api/dashboard/unknown"),
+            Err(RouteMiss::NotFound)
+        ));
+    }
+
+    #[test]
+    fn api_key_expiry_must_be_a_bounded_number_of_days() {
+        let with_expiry = |expires_in_days| NewApiKeyData {
+            expires_in_days,
+            ..Default::default()
+        };
+
+        assert!(with_expiry(None).validate().is_ok());
+        assert!(with_expiry(Some(30)).validate().is_ok());
+        assert!(with_expiry(Some(0)).validate().is_err());
+        assert!(with_expiry(Some(-1)).validate().is_err());
+        assert!(with_expiry(Some(i64::MAX)).validate().is_err());
+    }
 }
@@ -1,14 +1,16 @@
-use aws_lambda_events::{
-    apigw::ApiGatewayProxyResponse,
-    http::{Method, StatusCode},
-};
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
 use dotenvy::dotenv;
-use lambda_runtime::{service_fn, Error, LambdaEvent};
-use reqwest;
+use lambda_runtime::{Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use shared_lib::{utils::cors::cors, AppErrorResponse, AppSuccessResponse, RequestPayload};
+use shared_lib::{
+    error::AppError,
+    layers::{BodyLimitLayer, CatchPanicLayer, CorsLayer, RequestIdLayer, TimingLayer},
+    router::{extract::Json, request::RouteRequest, Router},
+    AppSuccessResponse, RequestPayload,
+};
 use std::{env, fmt::Debug};
+use tower::ServiceBuilder;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct RequestBody {
@@ -34,32 +36,16 @@ impl RustCodeExecuteRequestData {
     }
 }
 
-async fn handler(event: LambdaEvent<RequestPayload>) -> Result<ApiGatewayProxyResponse, Error> {
-    let http_method = event.payload.http_method.unwrap_or_default().to_uppercase();
-    let path = event.payload.path.unwrap_or_default();
+async fn execute_code(request: RouteRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    let rust_code_execution_url = env::var("RUST_CODE_EXECUTION_URL").unwrap_or_default();
 
-    if path != "/api/playground/execute-code" {
-        return AppErrorResponse::new(StatusCode::NOT_FOUND, Some("Not found".to_owned()), None);
-    }
+    let Json(request_body) = request.extract::<Json<RequestBody>>()?;
 
-    if http_method == Method::OPTIONS.to_string() {
-        return cors();
+    if request_body.code.is_empty() {
+        return AppError::BadRequest("Provide rust code".to_owned()).into_response();
     }
 
-    let rust_code_execution_url = env::var("RUST_CODE_EXECUTION_URL").unwrap_or_default();
-
-    let rust_code_from_request = if let Some(json_body) = event.payload.body {
-        let body_from_json: RequestBody =
-            serde_json::from_str(json_body.as_str()).unwrap_or_default();
-        let code = body_from_json.code;
-        code
-    } else {
-        return AppErrorResponse::new(
-            StatusCode::BAD_REQUEST,
-            Some("Provide rust code".to_owned()),
-            None,
-        );
-    };
+    let rust_code_from_request = request_body.code;
 
     let rust_code_execute_request_data =
         RustCodeExecuteRequestData::new(rust_code_from_request.to_owned());
@@ -71,25 +57,34 @@ async fn handler(event: LambdaEvent<RequestPayload>) -> Result<ApiGatewayProxyRe
         .send()
         .await?;
 
-    let data: Value = response.json().await.unwrap();
-    let http_method_to_enum = Method::from_bytes(http_method.as_bytes()).unwrap_or_default();
+    let data: Value = response.json().await?;
 
-    match http_method_to_enum {
-        Method::POST => AppSuccessResponse::new(
-            StatusCode::OK,
-            Some("Request successful".to_owned()),
-            Some(data),
-        ),
-        _ => AppErrorResponse::new(
-            StatusCode::NOT_ACCEPTABLE,
-            Some("Not acceptable".to_owned()),
-            None,
-        ),
-    }
+    AppSuccessResponse::new(
+        StatusCode::OK,
+        Some("Request successful".to_owned()),
+        Some(data),
+    )
+}
+
+async fn handler(event: LambdaEvent<RequestPayload>) -> Result<ApiGatewayProxyResponse, Error> {
+    Router::new()
+        .post("/api/playground/execute-code", |request, _| {
+            execute_code(request)
+        })
+        .handle(event, ())
+        .await
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     dotenv().unwrap_or_default();
-    lambda_runtime::run(service_fn(handler)).await
+    let service = ServiceBuilder::new()
+        .layer(RequestIdLayer)
+        .layer(TimingLayer)
+        .layer(CorsLayer)
+        .layer(CatchPanicLayer)
+        .layer(BodyLimitLayer::default())
+        .service_fn(handler);
+
+    lambda_runtime::run(service).await
 }
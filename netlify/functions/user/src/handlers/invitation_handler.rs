@@ -0,0 +1,103 @@
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use chrono::Utc;
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, to_bson},
+    Database,
+};
+use shared_lib::{
+    error::AppError,
+    models::{invitation::Invitation, user::User},
+    traits::model_traits::ModelTraits,
+    utils::{password_hasher::PasswordHasher, password_policy::NewPassword, token::hash_token},
+};
+use validator::Validate;
+
+use crate::{handlers::user_handler::add_user, RedeemInvitationData};
+
+pub async fn redeem_invitation(
+    database: &Database,
+    redeem_invitation_data: RedeemInvitationData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let token = redeem_invitation_data.token.unwrap_or_default();
+    let token_hash = hash_token(&token);
+
+    let invitation = match Invitation::find::<Invitation>(
+        database,
+        doc! {"token_hash": &token_hash, "redeemed_at": null},
+        None,
+        None,
+        1,
+    )
+    .await
+    {
+        Ok(invitations) => invitations.into_iter().next(),
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let invitation = match invitation {
+        Some(invitation) if !invitation.is_expired() => invitation,
+        _ => {
+            return AppError::BadRequest("Invitation is invalid or has expired".to_string())
+                .into_response()
+        }
+    };
+
+    let new_password = NewPassword {
+        password: redeem_invitation_data.password.clone().unwrap_or_default(),
+        username: redeem_invitation_data.username.clone(),
+        email: invitation.email.clone(),
+    };
+
+    if let Err(errors) = new_password.validate() {
+        return AppError::from(errors).into_response();
+    }
+
+    let now = Utc::now();
+
+    // Claim the invitation before creating the user so it can only be used once.
+    let claim_result = Invitation::update_one(
+        database,
+        doc! {"_id": invitation.id, "redeemed_at": null},
+        doc! {"$set": {
+            "redeemed_at": to_bson(&now).unwrap_or_default(),
+            "updated_at": to_bson(&now).unwrap_or_default(),
+        }},
+    )
+    .await;
+
+    match claim_result {
+        Ok(update_result) if update_result.modified_count == 1 => (),
+        Ok(_) => {
+            return AppError::BadRequest("Invitation is invalid or has expired".to_string())
+                .into_response()
+        }
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let hashed_password = PasswordHasher::from_env().hash(&new_password.password)?;
+
+    // The invitation was delivered to this address, which proves ownership of it.
+    let new_user_data = User {
+        username: redeem_invitation_data.username,
+        email: invitation.email.clone(),
+        password: Some(hashed_password),
+        role: invitation.role.clone(),
+        email_verified_at: Some(now),
+        ..Default::default()
+    };
+
+    let response = add_user(database, new_user_data).await?;
+
+    if response.status_code != StatusCode::OK.as_u16() as i64 {
+        // Release the invitation so the invitee can retry with different details.
+        Invitation::update_one(
+            database,
+            doc! {"_id": invitation.id},
+            doc! {"$set": {"redeemed_at": null}},
+        )
+        .await?;
+    }
+
+    Ok(response)
+}
@@ -1 +1,4 @@
-pub mod user_handler;
\ No newline at end of file
+pub mod invitation_handler;
+pub mod privacy_handler;
+pub mod profile_handler;
+pub mod user_handler;
@@ -0,0 +1,290 @@
+use std::env;
+
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    encodings::Body,
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderValue, StatusCode,
+    },
+};
+use chrono::{Duration, Utc};
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson, Document},
+    Database,
+};
+use serde::Deserialize;
+use serde_json::json;
+use shared_lib::{
+    auth::context::AuthContext,
+    error::AppError,
+    models::{
+        api_key::ApiKey,
+        audit_event::{AuditAction, AuditEvent},
+        impersonation_session::ImpersonationSession,
+        post::Post,
+        refresh_token::RefreshToken,
+        user::{erased_username, User, UserProfile, DELETED_USER_USERNAME},
+    },
+    traits::model_traits::ModelTraits,
+    utils::{password_hasher::PasswordHasher, token::generate_token},
+    AppSuccessResponse,
+};
+
+use crate::EraseAccountData;
+
+const EXPORT_LIMIT: i64 = 10_000;
+
+/// Returns everything stored about the logged-in user as a downloadable JSON
+/// file, stamped with the id of the request that produced it.
+pub async fn export_account_data(
+    database: &Database,
+    auth_context: &AuthContext,
+    request_id: String,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user_id = auth_context.user_id;
+
+    let profile = User::find::<UserProfile>(
+        database,
+        doc! {"_id": user_id},
+        Some(User::safe_projection()),
+        None,
+        1,
+    )
+    .await?;
+
+    let posts = Post::find::<Document>(
+        database,
+        doc! {"published_by": user_id},
+        None,
+        Some(doc! {"created_at": -1}),
+        EXPORT_LIMIT,
+    )
+    .await?;
+
+    let refresh_tokens = RefreshToken::find::<Document>(
+        database,
+        doc! {"user_id": user_id},
+        Some(doc! {"token_hash": false}),
+        Some(doc! {"created_at": -1}),
+        EXPORT_LIMIT,
+    )
+    .await?;
+
+    let api_keys = ApiKey::find::<Document>(
+        database,
+        doc! {"user_id": user_id},
+        Some(doc! {"secret_hash": false}),
+        Some(doc! {"created_at": -1}),
+        EXPORT_LIMIT,
+    )
+    .await?;
+
+    let impersonation_sessions = ImpersonationSession::find::<Document>(
+        database,
+        doc! {"$or": [{"user_id": user_id}, {"impersonator_id": user_id}]},
+        None,
+        Some(doc! {"created_at": -1}),
+        EXPORT_LIMIT,
+    )
+    .await?;
+
+    let audit_events = AuditEvent::find::<Document>(
+        database,
+        doc! {"$or": [
+            {"actor_id": user_id},
+            {"impersonator_id": user_id},
+            {"target_type": "user", "target_id": user_id.to_hex()},
+        ]},
+        None,
+        Some(doc! {"created_at": -1}),
+        EXPORT_LIMIT,
+    )
+    .await?;
+
+    let export = json!({
+        "exported_at": Utc::now(),
+        "request_id": request_id,
+        "profile": profile.first(),
+        "posts": posts,
+        "sessions": {
+            "refresh_tokens": refresh_tokens,
+            "api_keys": api_keys,
+            "impersonation_sessions": impersonation_sessions,
+        },
+        "audit_events": audit_events,
+    });
+
+    let mut response = AppSuccessResponse::new(StatusCode::OK, None, None)?;
+    let content_type = HeaderValue::from_static("application/json");
+    let content_disposition =
+        HeaderValue::from_static("attachment; filename=\"account-export.json\"");
+
+    for headers in [&mut response.headers, &mut response.multi_value_headers] {
+        headers.insert(CONTENT_TYPE, content_type.clone());
+        headers.insert(CONTENT_DISPOSITION, content_disposition.clone());
+    }
+    response.body = Some(Body::Text(serde_json::to_string_pretty(&export)?));
+
+    Ok(response)
+}
+
+/// Anonymizes the logged-in user straight away and schedules the remaining
+/// record for hard deletion once `ACCOUNT_ERASURE_GRACE_DAYS` have passed.
+pub async fn erase_account(
+    database: &Database,
+    auth_context: &AuthContext,
+    erase_account_data: EraseAccountData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    if auth_context.impersonation.is_some() {
+        return AppError::Forbidden("Accounts cannot be erased while impersonating".to_string())
+            .into_response();
+    }
+
+    let user = match User::find::<User>(database, doc! {"_id": auth_context.user_id}, None, None, 1)
+        .await?
+        .into_iter()
+        .next()
+    {
+        Some(user) => user,
+        None => return AppError::NotFound("User not found".to_string()).into_response(),
+    };
+
+    let password = erase_account_data.password.unwrap_or_default();
+    let password_hash = user.password.clone().unwrap_or_default();
+
+    if password.is_empty() || !PasswordHasher::from_env().verify(&password, &password_hash) {
+        return AppError::BadRequest("Password is incorrect".to_string()).into_response();
+    }
+
+    let user_id = auth_context.user_id;
+    let placeholder_author_id = find_or_create_placeholder_author(database).await?;
+
+    // Posts stay published, credited to the placeholder author instead.
+    Post::update_many(
+        database,
+        doc! {"published_by": user_id},
+        doc! {"$addToSet": {"published_by": placeholder_author_id}},
+    )
+    .await?;
+
+    Post::update_many(
+        database,
+        doc! {"published_by": user_id},
+        doc! {"$pull": {"published_by": user_id}},
+    )
+    .await?;
+
+    let grace_days = env::var("ACCOUNT_ERASURE_GRACE_DAYS")
+        .unwrap_or_default()
+        .parse::<i64>()
+        .unwrap_or(30);
+
+    let now = Utc::now();
+
+    User::update_one(
+        database,
+        doc! {"_id": user_id},
+        doc! {
+            "$set": {
+                "username": erased_username(&user_id),
+                "email": format!("{}@deleted.invalid", user_id.to_hex()),
+                "is_active": false,
+                "erased_at": to_bson(&now).unwrap_or_default(),
+                "purge_after": to_bson(&(now + Duration::days(grace_days))).unwrap_or_default(),
+                "updated_at": to_bson(&now).unwrap_or_default(),
+            },
+            "$unset": {
+                "password": "",
+                "profile_image": "",
+                "email_verified_at": "",
+                "password_reset_token_hash": "",
+                "password_reset_expires_at": "",
+            },
+        },
+    )
+    .await?;
+
+    revoke_credentials(database, user_id).await?;
+    AuditEvent::scrub_request_metadata(database, user_id).await?;
+
+    AuditEvent {
+        action: Some(AuditAction::AccountErased),
+        target_type: Some("user".to_owned()),
+        target_id: Some(user_id.to_hex()),
+        after: Some(doc! {"posts_reassigned_to": placeholder_author_id}),
+        ..AuditEvent::by(auth_context)
+    }
+    .record(database)
+    .await;
+
+    AppSuccessResponse::new(
+        StatusCode::OK,
+        Some(format!(
+            "Account erased. Remaining records are deleted in {} days",
+            grace_days
+        )),
+        None,
+    )
+}
+
+async fn revoke_credentials(database: &Database, user_id: ObjectId) -> Result<(), Error> {
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+    let revoke: Document = doc! {"$set": {"revoked_at": now.clone(), "updated_at": now}};
+
+    RefreshToken::update_many(
+        database,
+        doc! {"user_id": user_id, "revoked_at": null},
+        revoke.clone(),
+    )
+    .await?;
+
+    ApiKey::update_many(
+        database,
+        doc! {"user_id": user_id, "revoked_at": null},
+        revoke,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// The inactive account that erased users' posts are credited to.
+async fn find_or_create_placeholder_author(database: &Database) -> Result<ObjectId, Error> {
+    let filter = doc! {"username": DELETED_USER_USERNAME};
+
+    if let Some(user_id) = find_user_id(database, filter.clone()).await? {
+        return Ok(user_id);
+    }
+
+    let placeholder_author = User {
+        username: Some(DELETED_USER_USERNAME.to_owned()),
+        email: Some(format!("{}@deleted.invalid", DELETED_USER_USERNAME)),
+        password: Some(PasswordHasher::from_env().hash(&generate_token())?),
+        is_active: Some(false),
+        ..Default::default()
+    };
+
+    // A concurrent erasure may have created it first; the unique index keeps one.
+    if let Err(error) = placeholder_author.save(database).await {
+        eprintln!("Failed to create placeholder author: {:?}", error);
+    }
+
+    match find_user_id(database, filter).await? {
+        Some(user_id) => Ok(user_id),
+        None => Err("Placeholder author could not be created".into()),
+    }
+}
+
+#[derive(Deserialize)]
+struct UserId {
+    #[serde(rename = "_id")]
+    id: ObjectId,
+}
+
+async fn find_user_id(database: &Database, filter: Document) -> Result<Option<ObjectId>, Error> {
+    let users = User::find::<UserId>(database, filter, Some(doc! {"_id": true}), None, 1).await?;
+
+    Ok(users.into_iter().next().map(|user| user.id))
+}
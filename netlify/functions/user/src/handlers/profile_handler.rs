@@ -0,0 +1,229 @@
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    http::{header::SET_COOKIE, HeaderValue, StatusCode},
+};
+use chrono::Utc;
+use lambda_runtime::Error;
+use mongodb::{
+    bson::{doc, to_bson},
+    Database,
+};
+use serde_json::json;
+use shared_lib::{
+    auth::context::AuthContext,
+    error::AppError,
+    models::{
+        refresh_token::RefreshToken,
+        user::{User, UserProfile},
+    },
+    traits::model_traits::ModelTraits,
+    utils::{
+        cookie::{session_cookie, CookieKeys},
+        password_hasher::PasswordHasher,
+        password_policy::NewPassword,
+    },
+    AppSuccessResponse,
+};
+use validator::Validate;
+
+use crate::{
+    handlers::user_handler::send_verification_email, ChangePasswordData, UpdateProfileData,
+};
+
+pub async fn get_profile(
+    database: &Database,
+    auth_context: &AuthContext,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let users_response = User::find::<UserProfile>(
+        database,
+        doc! {"_id": auth_context.user_id},
+        Some(User::safe_projection()),
+        None,
+        1,
+    )
+    .await;
+
+    match users_response {
+        Ok(users) => match users.first() {
+            Some(user) => AppSuccessResponse::new(
+                StatusCode::OK,
+                Some("Request successful".to_string()),
+                Some(json!({
+                    "user": user
+                })),
+            ),
+            None => AppError::NotFound("User not found".to_string()).into_response(),
+        },
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+/// Updates the caller's profile. `has_session_cookie` tells whether the
+/// caller signed in with the session cookie, which holds the username and is
+/// re-issued when the username changes.
+pub async fn update_profile(
+    database: &Database,
+    auth_context: &AuthContext,
+    update_profile_data: UpdateProfileData,
+    has_session_cookie: bool,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // Changing the email unverifies it, which would lock the user out.
+    if auth_context.impersonation.is_some()
+        && (update_profile_data.username.is_some() || update_profile_data.email.is_some())
+    {
+        return AppError::Forbidden(
+            "Usernames and emails cannot be changed while impersonating".to_string(),
+        )
+        .into_response();
+    }
+
+    let mut user = match find_current_user(database, auth_context).await {
+        Some(user) => user,
+        None => return AppError::NotFound("User not found".to_string()).into_response(),
+    };
+
+    let email_changed = update_profile_data
+        .email
+        .as_ref()
+        .is_some_and(|email| Some(email) != user.email.as_ref());
+
+    let username_changed = update_profile_data
+        .username
+        .as_ref()
+        .is_some_and(|username| Some(username) != user.username.as_ref());
+
+    if let Some(username) = update_profile_data.username {
+        user.username = Some(username);
+    }
+
+    if let Some(email) = update_profile_data.email {
+        user.email = Some(email);
+    }
+
+    if let Some(profile_image) = update_profile_data.profile_image {
+        user.profile_image = Some(profile_image);
+    }
+
+    if let Err(errors) = user.validate() {
+        return AppError::from(errors).into_response();
+    }
+
+    let mut changes = doc! {
+        "username": user.username.clone(),
+        "email": user.email.clone(),
+        "profile_image": user.profile_image.clone(),
+        "updated_at": to_bson(&Utc::now()).unwrap_or_default(),
+    };
+
+    // A new address has to be confirmed again before it counts as verified.
+    if email_changed {
+        changes.insert("email_verified_at", None::<String>);
+    }
+
+    let update_result =
+        User::update_one(database, doc! {"_id": user.id}, doc! {"$set": changes}).await;
+
+    match update_result {
+        Ok(_) => {
+            if email_changed {
+                let email = user.email.clone().unwrap_or_default();
+
+                if let Err(error) = send_verification_email(&email).await {
+                    eprintln!("Failed to send verification email: {}", error);
+                }
+            }
+
+            let mut response = get_profile(database, auth_context).await?;
+
+            // The old cookie names a username that no longer exists.
+            if username_changed && has_session_cookie {
+                let cookie_value = CookieKeys::from_env()?
+                    .encrypt(session_cookie(user.username.clone().unwrap_or_default()));
+
+                response
+                    .headers
+                    .insert(SET_COOKIE, HeaderValue::from_str(&cookie_value)?);
+            }
+
+            Ok(response)
+        }
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+pub async fn change_password(
+    database: &Database,
+    auth_context: &AuthContext,
+    change_password_data: ChangePasswordData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // The current password is the user's to prove, not an impersonator's.
+    if auth_context.impersonation.is_some() {
+        return AppError::Forbidden("Passwords cannot be changed while impersonating".to_string())
+            .into_response();
+    }
+
+    let user = match find_current_user(database, auth_context).await {
+        Some(user) => user,
+        None => return AppError::NotFound("User not found".to_string()).into_response(),
+    };
+
+    let password_hasher = PasswordHasher::from_env();
+
+    let current_password = change_password_data.current_password.unwrap_or_default();
+    let password_hash = user.password.clone().unwrap_or_default();
+
+    if current_password.is_empty() || !password_hasher.verify(&current_password, &password_hash) {
+        return AppError::BadRequest("Current password is incorrect".to_string()).into_response();
+    }
+
+    let password = match change_password_data.new_password {
+        Some(password) if !password.is_empty() => password,
+        _ => return AppError::BadRequest("New password is required".to_string()).into_response(),
+    };
+
+    let new_password = NewPassword {
+        password: password.clone(),
+        username: user.username.clone(),
+        email: user.email.clone(),
+    };
+
+    if let Err(errors) = new_password.validate() {
+        return AppError::from(errors).into_response();
+    }
+
+    let hashed_password = password_hasher.hash(&password)?;
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    let update_result = User::update_one(
+        database,
+        doc! {"_id": user.id},
+        doc! {"$set": {
+            "password": hashed_password,
+            "updated_at": now.clone(),
+        }},
+    )
+    .await;
+
+    match update_result {
+        Ok(_) => {
+            // Sign out other devices holding a refresh token for the old password.
+            RefreshToken::update_many(
+                database,
+                doc! {"user_id": user.id, "revoked_at": null},
+                doc! {"$set": {"revoked_at": now.clone(), "updated_at": now}},
+            )
+            .await?;
+
+            AppSuccessResponse::new(StatusCode::OK, Some("Password updated".to_string()), None)
+        }
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+async fn find_current_user(database: &Database, auth_context: &AuthContext) -> Option<User> {
+    User::find::<User>(database, doc! {"_id": auth_context.user_id}, None, None, 1)
+        .await
+        .ok()?
+        .into_iter()
+        .next()
+}
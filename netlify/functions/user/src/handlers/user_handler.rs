@@ -1,64 +1,210 @@
+use std::env;
+
 use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use chrono::{Duration, Utc};
 use lambda_runtime::Error;
 use mongodb::{
-    bson::{doc, from_document},
+    bson::{doc, to_bson},
     Database,
 };
-use serde_json::json;
 use shared_lib::{
-    models::{
-        post::Post,
-        user::{User, UserRole},
-    },
+    error::AppError,
+    mailer::{EmailMessage, MailTransport, Mailer},
+    models::user::User,
     traits::model_traits::ModelTraits,
-    AppErrorResponse, AppSuccessResponse, DataInsertError,
+    utils::{
+        password_hasher::PasswordHasher,
+        password_policy::NewPassword,
+        signed_token::{create_signed_token, verify_signed_token},
+        token::hash_token,
+    },
+    AppSuccessResponse,
 };
 
-use crate::UserLoginData;
+use validator::Validate;
+
+use crate::ResetPasswordData;
+
+const EMAIL_VERIFICATION_PURPOSE: &str = "email_verification";
 
 pub async fn add_user(
     database: &Database,
     new_user_data: User,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let result = new_user_data.save(&database).await;
+    let result = new_user_data.save(database).await;
 
-    let _ = match result {
+    match result {
         Ok(_insert_value) => {
-            return AppSuccessResponse::new(
+            if new_user_data.email_verified_at.is_none() {
+                let email = new_user_data.email.clone().unwrap_or_default();
+
+                if let Err(error) = send_verification_email(&email).await {
+                    eprintln!("Failed to send verification email: {}", error);
+                }
+            }
+
+            AppSuccessResponse::new(
                 StatusCode::OK,
                 Some("Data added successfully".to_string()),
                 None,
             )
         }
-        Err(error) => match error {
-            DataInsertError::FieldValidationError(error) => {
-                return AppErrorResponse::new(
-                    StatusCode::BAD_REQUEST,
-                    Some("An error occured".to_string()),
-                    Some(json!({
-                        "errors": error
-                    })),
-                )
-            }
-            DataInsertError::MongoDuplicateError(mut error) if error.code == 11000 => {
-                let duplicate_field_data =
-                    error.message.split("key: ").collect::<Vec<&str>>()[1].to_owned();
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
 
-                let duplicate_field_value =
-                    duplicate_field_data.split(" ").collect::<Vec<&str>>()[2].to_owned();
+pub async fn send_verification_email(email: &str) -> Result<(), Error> {
+    let verification_secret = env::var("EMAIL_VERIFICATION_SECRET").unwrap_or_default();
+    let api_base_url = env::var("API_BASE_URL").unwrap_or_default();
+    let ttl_hours = env::var("EMAIL_VERIFICATION_TTL_HOURS")
+        .unwrap_or_default()
+        .parse::<i64>()
+        .unwrap_or(24);
 
-                error.message =
-                    format!("An error occured. {} already exists", duplicate_field_value);
+    let token = create_signed_token(
+        email,
+        EMAIL_VERIFICATION_PURPOSE,
+        Duration::hours(ttl_hours),
+        &verification_secret,
+    )
+    .ok_or("EMAIL_VERIFICATION_SECRET is not set")?;
 
-                return AppErrorResponse::new(StatusCode::BAD_REQUEST, Some(error.message), None);
-            }
-            _ => {
-                return AppErrorResponse::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Some("An error occured".to_string()),
-                    None,
-                )
+    let verification_link = format!("{}/api/user/verify-email?token={}", api_base_url, token);
+
+    let message = EmailMessage::new(
+        email.to_owned(),
+        "Verify your email address".to_owned(),
+        format!(
+            "Confirm your email address by opening the link below. The link expires in {} hours.\n\n{}",
+            ttl_hours, verification_link
+        ),
+    );
+
+    Mailer::from_env().send(&message).await?;
+
+    Ok(())
+}
+
+pub async fn verify_email(
+    database: &Database,
+    token: String,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let verification_secret = env::var("EMAIL_VERIFICATION_SECRET").unwrap_or_default();
+
+    let email = match verify_signed_token(&token, EMAIL_VERIFICATION_PURPOSE, &verification_secret)
+    {
+        Some(email) => email,
+        None => {
+            return AppError::BadRequest("Verification link is invalid or has expired".to_string())
+                .into_response()
+        }
+    };
+
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    let update_result = User::update_one(
+        database,
+        doc! {"email": &email, "email_verified_at": null},
+        doc! {"$set": {
+            "email_verified_at": now.clone(),
+            "updated_at": now,
+        }},
+    )
+    .await;
+
+    match update_result {
+        Ok(update_result) if update_result.matched_count > 0 => AppSuccessResponse::new(
+            StatusCode::OK,
+            Some("Email address verified".to_string()),
+            None,
+        ),
+        // Nothing matched: the address is already verified, or the account
+        // changed its email or was erased since the link was sent.
+        Ok(_) => match User::count_documents(database, doc! {"email": &email}).await {
+            Ok(0) => {
+                AppError::NotFound("No account uses this email address".to_string()).into_response()
             }
+            Ok(_) => AppError::BadRequest("Email address is already verified".to_string())
+                .into_response(),
+            Err(error) => AppError::from(error).into_response(),
         },
+        Err(error) => AppError::from(error).into_response(),
+    }
+}
+
+pub async fn reset_password(
+    database: &Database,
+    reset_password_data: ResetPasswordData,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let token_hash = hash_token(&reset_password_data.token.unwrap_or_default());
+
+    let user = match User::find::<User>(
+        database,
+        doc! {"password_reset_token_hash": &token_hash},
+        None,
+        None,
+        1,
+    )
+    .await
+    {
+        Ok(users) => users.into_iter().next(),
+        Err(error) => return AppError::from(error).into_response(),
+    };
+
+    let user = match user {
+        Some(user)
+            if user
+                .password_reset_expires_at
+                .is_some_and(|expires_at| expires_at > Utc::now()) =>
+        {
+            user
+        }
+        _ => {
+            return AppError::BadRequest(
+                "Password reset link is invalid or has expired".to_string(),
+            )
+            .into_response()
+        }
+    };
+
+    let password = match reset_password_data.password {
+        Some(password) if !password.is_empty() => password,
+        _ => return AppError::BadRequest("Password is required".to_string()).into_response(),
     };
+
+    let new_password = NewPassword {
+        password: password.clone(),
+        username: user.username.clone(),
+        email: user.email.clone(),
+    };
+
+    if let Err(errors) = new_password.validate() {
+        return AppError::from(errors).into_response();
+    }
+
+    let hashed_password = PasswordHasher::from_env().hash(&password)?;
+
+    let update_result = User::update_one(
+        database,
+        doc! {"_id": user.id, "password_reset_token_hash": &token_hash},
+        doc! {
+            "$set": {
+                "password": hashed_password,
+                "password_reset_required": false,
+                "updated_at": to_bson(&Utc::now()).unwrap_or_default(),
+            },
+            "$unset": {
+                "password_reset_token_hash": "",
+                "password_reset_expires_at": "",
+            },
+        },
+    )
+    .await;
+
+    match update_result {
+        Ok(_) => {
+            AppSuccessResponse::new(StatusCode::OK, Some("Password updated".to_string()), None)
+        }
+        Err(error) => AppError::from(error).into_response(),
+    }
 }
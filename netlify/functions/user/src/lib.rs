@@ -1,9 +1,141 @@
 use serde::{Deserialize, Serialize};
+use shared_lib::{
+    auth::csrf::csrf_token_response,
+    layers::RequestIdHeader,
+    router::{
+        context::RequestContext,
+        extract::{Cookie, Header, Json, Query},
+        Access, Router,
+    },
+    utils::cookie::SessionCookie,
+};
 
 pub mod handlers;
 
+use crate::handlers::{
+    invitation_handler::redeem_invitation,
+    privacy_handler::{erase_account, export_account_data},
+    profile_handler::{change_password, get_profile, update_profile},
+    user_handler::{reset_password, verify_email},
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct UserLoginData {
     pub username: Option<String>,
     pub password: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VerifyEmailQueryParams {
+    #[serde(default)]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RedeemInvitationData {
+    pub token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ResetPasswordData {
+    pub token: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateProfileData {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub profile_image: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChangePasswordData {
+    pub current_password: Option<String>,
+    pub new_password: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EraseAccountData {
+    pub password: Option<String>,
+}
+
+/// Every user route. The `/api/user/me` routes act on the logged-in user.
+pub fn router() -> Router<RequestContext> {
+    Router::<RequestContext>::new()
+        .get("/api/user/csrf-token", |_, _| async {
+            csrf_token_response()
+        })
+        .get("/api/user/verify-email", |request, context| async move {
+            let Query(verify_email_query_params) =
+                request.extract::<Query<VerifyEmailQueryParams>>()?;
+
+            verify_email(&context.database, verify_email_query_params.token).await
+        })
+        .post(
+            "/api/user/invitations/redeem",
+            |request, context| async move {
+                let Json(redeem_invitation_data) =
+                    request.extract::<Json<RedeemInvitationData>>()?;
+
+                redeem_invitation(&context.database, redeem_invitation_data).await
+            },
+        )
+        .post("/api/user/password-reset", |request, context| async move {
+            let Json(reset_password_data) = request.extract::<Json<ResetPasswordData>>()?;
+
+            reset_password(&context.database, reset_password_data).await
+        })
+        .get_guarded(
+            "/api/user/me",
+            Access::Authenticated,
+            |_, context, auth_context| async move {
+                get_profile(&context.database, &auth_context).await
+            },
+        )
+        .patch_guarded(
+            "/api/user/me",
+            Access::Authenticated,
+            |request, context, auth_context| async move {
+                let Json(update_profile_data) = request.extract::<Json<UpdateProfileData>>()?;
+                let session_cookie = request.extract::<Option<Cookie<SessionCookie>>>()?;
+
+                update_profile(
+                    &context.database,
+                    &auth_context,
+                    update_profile_data,
+                    session_cookie.is_some(),
+                )
+                .await
+            },
+        )
+        .get_guarded(
+            "/api/user/me/export",
+            Access::Authenticated,
+            |request, context, auth_context| async move {
+                let Header(request_id, _) = request.extract::<Header<RequestIdHeader>>()?;
+
+                export_account_data(&context.database, &auth_context, request_id).await
+            },
+        )
+        .post_guarded(
+            "/api/user/me/password",
+            Access::Authenticated,
+            |request, context, auth_context| async move {
+                let Json(change_password_data) = request.extract::<Json<ChangePasswordData>>()?;
+
+                change_password(&context.database, &auth_context, change_password_data).await
+            },
+        )
+        .post_guarded(
+            "/api/user/me/erasure",
+            Access::Authenticated,
+            |request, context, auth_context| async move {
+                let Json(erase_account_data) = request.extract::<Json<EraseAccountData>>()?;
+
+                erase_account(&context.database, &auth_context, erase_account_data).await
+            },
+        )
+}
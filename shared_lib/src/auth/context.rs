@@ -0,0 +1,78 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::permission::{role_permissions, Permission},
+    models::user::UserRole,
+};
+
+/// Set when a super admin acts as another user.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Impersonation {
+    pub session_id: ObjectId,
+    pub impersonator_id: ObjectId,
+    pub allow_destructive: bool,
+}
+
+/// Who is making the request and what they may do.
+///
+/// Cookie sessions carry every permission of the user's role, while API keys
+/// are limited to the scopes chosen when the key was created. While
+/// impersonating, `user_id` and `role` are the effective user's and the real
+/// user is kept in `impersonation`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthContext {
+    pub user_id: ObjectId,
+    pub role: UserRole,
+    pub scopes: Vec<Permission>,
+    #[serde(default)]
+    pub impersonation: Option<Impersonation>,
+}
+
+impl AuthContext {
+    pub fn for_session(user_id: ObjectId, role: UserRole) -> Self {
+        let scopes = role_permissions(&role).to_vec();
+
+        Self {
+            user_id,
+            role,
+            scopes,
+            impersonation: None,
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        let blocked_by_impersonation = self.impersonation.as_ref().is_some_and(|impersonation| {
+            !impersonation.allow_destructive && permission.is_destructive()
+        });
+
+        self.role.has_permission(permission)
+            && self.scopes.contains(&permission)
+            && !blocked_by_impersonation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn impersonating(allow_destructive: bool) -> AuthContext {
+        AuthContext {
+            impersonation: Some(Impersonation {
+                session_id: ObjectId::new(),
+                impersonator_id: ObjectId::new(),
+                allow_destructive,
+            }),
+            ..AuthContext::for_session(ObjectId::new(), UserRole::Admin)
+        }
+    }
+
+    #[test]
+    fn impersonation_blocks_destructive_permissions_unless_allowed() {
+        assert!(impersonating(false).can(Permission::EditPost));
+        assert!(!impersonating(false).can(Permission::PublishPost));
+        assert!(!impersonating(false).can(Permission::ManageApiKeys));
+        assert!(impersonating(true).can(Permission::PublishPost));
+        assert!(!impersonating(true).can(Permission::ManageUsers));
+    }
+}
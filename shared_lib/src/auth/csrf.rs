@@ -0,0 +1,93 @@
+use std::env;
+
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    http::{header::SET_COOKIE, HeaderValue, Method, StatusCode},
+};
+use cookie::{time::Duration, Cookie};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+
+use crate::{
+    utils::{
+        cookie::{parse_private_cookie, same_site_from_env, CookieKeys},
+        headers::{get_header, parse_bearer_token},
+        token::{generate_token, hash_token},
+    },
+    AppSuccessResponse, RequestPayload,
+};
+
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+pub fn csrf_cookie_name() -> String {
+    format!("{}_csrf", env::var("COOKIE_NAME").unwrap_or_default())
+}
+
+/// Cookie holding the CSRF token. Encrypt it before sending.
+pub fn csrf_cookie(csrf_token: String) -> Cookie<'static> {
+    let mut cookie = Cookie::new(csrf_cookie_name(), csrf_token);
+    cookie.set_http_only(true);
+    cookie.set_secure(true);
+    cookie.set_same_site(same_site_from_env());
+    cookie.set_path("/");
+    cookie.set_max_age(Duration::days(1));
+
+    cookie
+}
+
+/// Issues a fresh CSRF token in the body and its encrypted twin as a cookie.
+/// Clients echo the body value back in the `X-CSRF-Token` header.
+pub fn csrf_token_response() -> Result<ApiGatewayProxyResponse, Error> {
+    let cookie_keys = CookieKeys::from_env()?;
+    let csrf_token = generate_token();
+    let cookie = csrf_cookie(csrf_token.clone());
+
+    let mut response = AppSuccessResponse::new(
+        StatusCode::OK,
+        None,
+        Some(json!({"csrf_token": csrf_token})),
+    )?;
+
+    response.headers.insert(
+        SET_COOKIE,
+        HeaderValue::from_str(&cookie_keys.encrypt(cookie))?,
+    );
+
+    Ok(response)
+}
+
+/// Double-submit check for requests that could ride on ambient cookies.
+///
+/// Safe methods, bearer authenticated requests and requests without any
+/// cookie pass. Everything else must send an `X-CSRF-Token` header matching
+/// the encrypted CSRF cookie.
+pub fn is_valid_csrf_request(event: &LambdaEvent<RequestPayload>) -> bool {
+    let http_method = event
+        .payload
+        .http_method
+        .clone()
+        .unwrap_or_default()
+        .to_uppercase();
+
+    if http_method == Method::GET.as_str()
+        || http_method == Method::HEAD.as_str()
+        || http_method == Method::OPTIONS.as_str()
+    {
+        return true;
+    }
+
+    if parse_bearer_token(event).is_some() || get_header(event, "cookie").is_none() {
+        return true;
+    }
+
+    match (
+        get_header(event, CSRF_HEADER),
+        parse_private_cookie(event, &csrf_cookie_name()),
+    ) {
+        // Compare digests so the check does not leak how much of the token matched.
+        (Some(header_token), Some(cookie_token)) => {
+            !header_token.is_empty() && hash_token(&header_token) == hash_token(&cookie_token)
+        }
+        _ => false,
+    }
+}
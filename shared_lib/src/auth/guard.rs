@@ -0,0 +1,152 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use chrono::Utc;
+use lambda_runtime::LambdaEvent;
+use mongodb::{
+    bson::{doc, to_bson, Document},
+    Database,
+};
+
+use crate::{
+    auth::{
+        context::AuthContext,
+        impersonation::{impersonation_cookie_name, resolve_impersonation},
+        jwt::JwtConfig,
+        permission::Permission,
+    },
+    error::AppError,
+    models::{api_key::ApiKey, user::User},
+    router::Access,
+    traits::model_traits::ModelTraits,
+    utils::{
+        cookie::{parse_cookie, parse_private_cookie},
+        headers::parse_bearer_token,
+        token::hash_token,
+    },
+    RequestPayload,
+};
+
+/// Message sent with the 401 for a request that reached a guarded route
+/// without credentials.
+pub const AUTHENTICATION_REQUIRED: &str = "Authentication is required";
+
+fn unauthorized() -> ApiGatewayProxyResponse {
+    AppError::Unauthorized(AUTHENTICATION_REQUIRED.to_owned())
+        .into_response()
+        .unwrap_or_default()
+}
+
+/// Resolves the caller a route's `access` asks for: nobody for public routes,
+/// and the authenticated caller otherwise. On failure the returned error is
+/// the response to send back.
+pub async fn authorize(
+    event: &LambdaEvent<RequestPayload>,
+    database: &Database,
+    access: Access,
+) -> Result<Option<AuthContext>, ApiGatewayProxyResponse> {
+    match access {
+        Access::Public => Ok(None),
+        Access::Authenticated => match authenticate(event, database).await {
+            Some(auth_context) => Ok(Some(auth_context)),
+            None => Err(unauthorized()),
+        },
+        Access::Guarded(permission) => require(event, database, permission).await.map(Some),
+    }
+}
+
+/// Authenticates the request and checks that it grants `permission`. On
+/// failure the returned error is the response to send back.
+pub async fn require(
+    event: &LambdaEvent<RequestPayload>,
+    database: &Database,
+    permission: Permission,
+) -> Result<AuthContext, ApiGatewayProxyResponse> {
+    let auth_context = match authenticate(event, database).await {
+        Some(auth_context) => auth_context,
+        None => return Err(unauthorized()),
+    };
+
+    if !auth_context.can(permission) {
+        return Err(AppError::Forbidden(
+            "You do not have permission to perform this action".to_string(),
+        )
+        .into_response()
+        .unwrap_or_default());
+    }
+
+    Ok(auth_context)
+}
+
+/// Resolves the caller from an `Authorization: Bearer` API key or access
+/// token or, failing that, from the session cookie.
+pub async fn authenticate(
+    event: &LambdaEvent<RequestPayload>,
+    database: &Database,
+) -> Option<AuthContext> {
+    if let Some(bearer_token) = parse_bearer_token(event) {
+        if ApiKey::parse_key(&bearer_token).is_some() {
+            return authenticate_api_key(database, &bearer_token).await;
+        }
+
+        return JwtConfig::from_env().verify_access_token(&bearer_token);
+    }
+
+    let username = parse_cookie(event)?;
+    let user = find_active_user(database, doc! {"username": username}).await?;
+
+    let auth_context = AuthContext::for_session(user.id?, user.role.unwrap_or_default());
+
+    // A stale or foreign impersonation cookie falls back to the real user.
+    match parse_private_cookie(event, &impersonation_cookie_name()) {
+        Some(session_id) => Some(
+            resolve_impersonation(database, &auth_context, &session_id)
+                .await
+                .unwrap_or(auth_context),
+        ),
+        None => Some(auth_context),
+    }
+}
+
+async fn authenticate_api_key(database: &Database, key: &str) -> Option<AuthContext> {
+    let (prefix, secret) = ApiKey::parse_key(key)?;
+
+    let api_key = ApiKey::find::<ApiKey>(database, doc! {"prefix": prefix}, None, None, 1)
+        .await
+        .ok()?
+        .into_iter()
+        .next()?;
+
+    if !api_key.is_usable() || api_key.secret_hash? != hash_token(secret) {
+        return None;
+    }
+
+    let user = find_active_user(database, doc! {"_id": api_key.user_id}).await?;
+
+    ApiKey::update_one(
+        database,
+        doc! {"_id": api_key.id},
+        doc! {"$set": {"last_used_at": to_bson(&Utc::now()).unwrap_or_default()}},
+    )
+    .await
+    .ok()?;
+
+    Some(AuthContext {
+        user_id: user.id?,
+        role: user.role.unwrap_or_default(),
+        scopes: api_key.scopes.unwrap_or_default(),
+        impersonation: None,
+    })
+}
+
+async fn find_active_user(database: &Database, filter: Document) -> Option<User> {
+    let user = User::find::<User>(database, filter, None, None, 1)
+        .await
+        .ok()?
+        .into_iter()
+        .next()?;
+
+    if !user.is_active() || user.password_reset_required.unwrap_or_default() {
+        return None;
+    }
+
+    Some(user)
+}
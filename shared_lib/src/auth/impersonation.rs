@@ -0,0 +1,160 @@
+use std::env;
+
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    encodings::Body,
+    http::{HeaderValue, StatusCode},
+};
+use cookie::{time::Duration, Cookie};
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    Database,
+};
+use serde_json::json;
+
+use crate::{
+    auth::{
+        context::{AuthContext, Impersonation},
+        permission::Permission,
+    },
+    models::{impersonation_session::ImpersonationSession, user::User},
+    router::extract::CookieName,
+    traits::model_traits::ModelTraits,
+    utils::cookie::same_site_from_env,
+    ResponseBody, ResponseStatus,
+};
+
+pub const IMPERSONATION_HEADER: &str = "X-Impersonated-By";
+
+pub fn impersonation_cookie_name() -> String {
+    format!(
+        "{}_impersonation",
+        env::var("COOKIE_NAME").unwrap_or_default()
+    )
+}
+
+/// Extracts the impersonation cookie, holding the impersonation session id.
+pub struct ImpersonationCookie;
+
+impl CookieName for ImpersonationCookie {
+    fn name() -> String {
+        impersonation_cookie_name()
+    }
+}
+
+/// How long an impersonation session lasts, from `IMPERSONATION_TTL_MINUTES`.
+pub fn impersonation_ttl_minutes() -> i64 {
+    env::var("IMPERSONATION_TTL_MINUTES")
+        .unwrap_or_default()
+        .parse::<i64>()
+        .unwrap_or(60)
+}
+
+/// Cookie holding the impersonation session id. Encrypt it before sending.
+pub fn impersonation_cookie(session_id: &ObjectId) -> Cookie<'static> {
+    let mut cookie = Cookie::new(impersonation_cookie_name(), session_id.to_hex());
+    cookie.set_http_only(true);
+    cookie.set_secure(true);
+    cookie.set_same_site(same_site_from_env());
+    cookie.set_path("/");
+    cookie.set_max_age(Duration::minutes(impersonation_ttl_minutes()));
+
+    cookie
+}
+
+/// Switches `real_context` to the impersonated user if `session_id` names an
+/// active session started by this very user, who must still be allowed to
+/// impersonate.
+pub async fn resolve_impersonation(
+    database: &Database,
+    real_context: &AuthContext,
+    session_id: &str,
+) -> Option<AuthContext> {
+    if !real_context.can(Permission::ImpersonateUsers) {
+        return None;
+    }
+
+    let session_id = ObjectId::parse_str(session_id).ok()?;
+
+    let impersonation_session = ImpersonationSession::find::<ImpersonationSession>(
+        database,
+        doc! {"_id": session_id, "impersonator_id": real_context.user_id},
+        None,
+        None,
+        1,
+    )
+    .await
+    .ok()?
+    .into_iter()
+    .next()?;
+
+    if !impersonation_session.is_active() {
+        return None;
+    }
+
+    let user = User::find::<User>(
+        database,
+        doc! {"_id": impersonation_session.user_id},
+        None,
+        None,
+        1,
+    )
+    .await
+    .ok()?
+    .into_iter()
+    .next()?;
+
+    if !user.is_active() {
+        return None;
+    }
+
+    Some(AuthContext {
+        impersonation: Some(Impersonation {
+            session_id,
+            impersonator_id: real_context.user_id,
+            allow_destructive: impersonation_session.allow_destructive.unwrap_or_default(),
+        }),
+        ..AuthContext::for_session(user.id?, user.role.unwrap_or_default())
+    })
+}
+
+/// Marks a successful response produced while impersonating, in the body and
+/// in the `X-Impersonated-By` header, so clients can always show it.
+pub fn flag_impersonated_response(
+    response: &mut ApiGatewayProxyResponse,
+    auth_context: &AuthContext,
+) {
+    let impersonation = match auth_context.impersonation.as_ref() {
+        Some(impersonation) => impersonation,
+        None => return,
+    };
+
+    if !StatusCode::from_u16(response.status_code as u16).is_ok_and(|status| status.is_success()) {
+        return;
+    }
+
+    if let Some(Body::Text(body)) = response.body.as_ref() {
+        if let Ok(mut response_body) = serde_json::from_str::<ResponseBody>(body) {
+            if matches!(response_body.status, ResponseStatus::Success) {
+                response_body.impersonation = Some(json!({
+                    "impersonator_id": impersonation.impersonator_id.to_hex(),
+                    "user_id": auth_context.user_id.to_hex(),
+                    "allow_destructive": impersonation.allow_destructive,
+                }));
+
+                response.body = Some(Body::Text(
+                    serde_json::to_string(&response_body).unwrap_or_default(),
+                ));
+            }
+        }
+    }
+
+    if let Ok(header_value) = HeaderValue::from_str(&impersonation.impersonator_id.to_hex()) {
+        response
+            .headers
+            .insert(IMPERSONATION_HEADER, header_value.clone());
+        response
+            .multi_value_headers
+            .insert(IMPERSONATION_HEADER, header_value);
+    }
+}
@@ -0,0 +1,122 @@
+use std::env;
+
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{context::AuthContext, permission::Permission},
+    models::user::UserRole,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccessTokenClaims {
+    pub sub: String,
+    pub role: UserRole,
+    pub scopes: Vec<Permission>,
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Signing configuration read from the environment.
+///
+/// `JWT_ALGORITHM` selects `HS256` (default, keyed by `JWT_SECRET`) or
+/// `EdDSA` (keyed by the PEM encoded `JWT_PRIVATE_KEY` and `JWT_PUBLIC_KEY`).
+pub struct JwtConfig {
+    pub algorithm: Algorithm,
+    pub issuer: String,
+    pub access_token_ttl_seconds: i64,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: Option<DecodingKey>,
+}
+
+impl JwtConfig {
+    pub fn from_env() -> Self {
+        let issuer = env::var("JWT_ISSUER").unwrap_or("lambda-netlify".to_owned());
+        let access_token_ttl_seconds = env::var("JWT_ACCESS_TOKEN_TTL_SECONDS")
+            .unwrap_or_default()
+            .parse::<i64>()
+            .unwrap_or(900);
+
+        let algorithm = env::var("JWT_ALGORITHM").unwrap_or_default();
+
+        let (algorithm, encoding_key, decoding_key) = match algorithm.to_uppercase().as_str() {
+            "EDDSA" => {
+                let private_key = env::var("JWT_PRIVATE_KEY").unwrap_or_default();
+                let public_key = env::var("JWT_PUBLIC_KEY").unwrap_or_default();
+
+                (
+                    Algorithm::EdDSA,
+                    EncodingKey::from_ed_pem(private_key.as_bytes()).ok(),
+                    DecodingKey::from_ed_pem(public_key.as_bytes()).ok(),
+                )
+            }
+            _ => {
+                let secret = env::var("JWT_SECRET").unwrap_or_default();
+
+                // An empty secret would make every token trivially forgeable.
+                if secret.is_empty() {
+                    (Algorithm::HS256, None, None)
+                } else {
+                    (
+                        Algorithm::HS256,
+                        Some(EncodingKey::from_secret(secret.as_bytes())),
+                        Some(DecodingKey::from_secret(secret.as_bytes())),
+                    )
+                }
+            }
+        };
+
+        Self {
+            algorithm,
+            issuer,
+            access_token_ttl_seconds,
+            encoding_key,
+            decoding_key,
+        }
+    }
+
+    pub fn issue_access_token(
+        &self,
+        auth_context: &AuthContext,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let encoding_key = self
+            .encoding_key
+            .as_ref()
+            .ok_or(jsonwebtoken::errors::ErrorKind::InvalidKeyFormat)?;
+
+        let now = Utc::now().timestamp();
+
+        let claims = AccessTokenClaims {
+            sub: auth_context.user_id.to_hex(),
+            role: auth_context.role.clone(),
+            scopes: auth_context.scopes.clone(),
+            iss: self.issuer.clone(),
+            iat: now,
+            exp: now + self.access_token_ttl_seconds,
+        };
+
+        encode(&Header::new(self.algorithm), &claims, encoding_key)
+    }
+
+    /// Verifies signature, issuer and expiry and returns the caller's context.
+    pub fn verify_access_token(&self, token: &str) -> Option<AuthContext> {
+        let decoding_key = self.decoding_key.as_ref()?;
+
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+
+        let claims = decode::<AccessTokenClaims>(token, decoding_key, &validation)
+            .ok()?
+            .claims;
+
+        Some(AuthContext {
+            user_id: ObjectId::parse_str(claims.sub).ok()?,
+            role: claims.role,
+            scopes: claims.scopes,
+            impersonation: None,
+        })
+    }
+}
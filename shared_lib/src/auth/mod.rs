@@ -0,0 +1,6 @@
+pub mod context;
+pub mod csrf;
+pub mod guard;
+pub mod impersonation;
+pub mod jwt;
+pub mod permission;
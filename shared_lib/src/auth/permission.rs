@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::user::UserRole;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum Permission {
+    #[serde(rename = "access_admin")]
+    AccessAdmin,
+    #[serde(rename = "view_dashboard")]
+    ViewDashboard,
+    #[serde(rename = "create_post")]
+    CreatePost,
+    #[serde(rename = "edit_post")]
+    EditPost,
+    #[serde(rename = "publish_post")]
+    PublishPost,
+    #[serde(rename = "manage_invitations")]
+    ManageInvitations,
+    #[serde(rename = "manage_users")]
+    ManageUsers,
+    #[serde(rename = "manage_api_keys")]
+    ManageApiKeys,
+    #[serde(rename = "view_audit_log")]
+    ViewAuditLog,
+    #[serde(rename = "impersonate_users")]
+    ImpersonateUsers,
+}
+
+impl Permission {
+    pub const ALL: &'static [Permission] = &[
+        Permission::AccessAdmin,
+        Permission::ViewDashboard,
+        Permission::CreatePost,
+        Permission::EditPost,
+        Permission::PublishPost,
+        Permission::ManageInvitations,
+        Permission::ManageUsers,
+        Permission::ManageApiKeys,
+        Permission::ViewAuditLog,
+        Permission::ImpersonateUsers,
+    ];
+
+    /// Permissions an impersonation session only gets when explicitly allowed.
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Permission::PublishPost
+                | Permission::ManageInvitations
+                | Permission::ManageUsers
+                | Permission::ManageApiKeys
+                | Permission::ImpersonateUsers
+        )
+    }
+}
+
+const SUPER_ADMIN_PERMISSIONS: &[Permission] = Permission::ALL;
+
+const ADMIN_PERMISSIONS: &[Permission] = &[
+    Permission::AccessAdmin,
+    Permission::ViewDashboard,
+    Permission::CreatePost,
+    Permission::EditPost,
+    Permission::PublishPost,
+    Permission::ManageInvitations,
+    Permission::ManageApiKeys,
+];
+
+const USER_PERMISSIONS: &[Permission] = &[];
+
+/// The single source of truth for what each role is allowed to do.
+pub fn role_permissions(role: &UserRole) -> &'static [Permission] {
+    match role {
+        UserRole::SuperAdmin => SUPER_ADMIN_PERMISSIONS,
+        UserRole::Admin => ADMIN_PERMISSIONS,
+        UserRole::User => USER_PERMISSIONS,
+    }
+}
+
+impl UserRole {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        role_permissions(self).contains(&permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLES: &[UserRole] = &[UserRole::SuperAdmin, UserRole::Admin, UserRole::User];
+
+    fn expected(role: &UserRole, permission: Permission) -> bool {
+        match (role, permission) {
+            (UserRole::SuperAdmin, _) => true,
+            (
+                UserRole::Admin,
+                Permission::ManageUsers | Permission::ViewAuditLog | Permission::ImpersonateUsers,
+            ) => false,
+            (UserRole::Admin, _) => true,
+            (UserRole::User, _) => false,
+        }
+    }
+
+    #[test]
+    fn every_role_and_permission_matches_the_matrix() {
+        for role in ROLES {
+            for permission in Permission::ALL {
+                assert_eq!(
+                    role.has_permission(*permission),
+                    expected(role, *permission),
+                    "{:?} / {:?}",
+                    role,
+                    permission
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn super_admin_can_log_in_to_admin() {
+        assert!(UserRole::SuperAdmin.has_permission(Permission::AccessAdmin));
+    }
+}
@@ -0,0 +1,102 @@
+use std::env;
+
+use dotenvy::dotenv;
+use lambda_runtime::Error;
+use shared_lib::database::{
+    client::connect_db,
+    indexes::{applied_indexes_version, apply_indexes, indexes_version},
+    migrations::{migrate, migration_status, rollback, MigrationRun},
+};
+
+const USAGE: &str = "Usage: schema <command>
+
+Commands:
+  status                          Show the index version and every migration
+  indexes [--force]               Apply the declared indexes if the database is behind
+  migrate [--to <name>] [--dry-run]
+                                  Apply pending migrations, optionally up to <name>
+  rollback [--steps <n>] [--dry-run]
+                                  Revert the last <n> applied migrations (default 1)";
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    dotenv().unwrap_or_default();
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|position| args.get(position + 1))
+            .map(String::as_str)
+    };
+
+    let dry_run = flag("--dry-run");
+
+    match args.first().map(String::as_str) {
+        Some("status") => {
+            let database = connect_db().await?;
+            let applied_version = applied_indexes_version(&database).await?;
+
+            println!(
+                "indexes: {} applied, {} declared",
+                applied_version.unwrap_or("none".to_owned()),
+                indexes_version()
+            );
+
+            for status in migration_status(&database).await? {
+                match status.applied_at {
+                    Some(applied_at) => println!("[x] {} ({})", status.name, applied_at),
+                    None => println!("[ ] {}", status.name),
+                }
+            }
+        }
+        Some("indexes") => {
+            let database = connect_db().await?;
+
+            let version = indexes_version();
+
+            match apply_indexes(&database, flag("--force")).await? {
+                true => println!("Applied indexes version {}", version),
+                false => println!("Indexes are up to date (version {})", version),
+            }
+        }
+        Some("migrate") => {
+            let database = connect_db().await?;
+            let runs = migrate(&database, option("--to"), dry_run).await?;
+
+            print_runs(&runs, "Applied", dry_run);
+        }
+        Some("rollback") => {
+            let steps = match option("--steps").map(str::parse::<usize>) {
+                Some(Ok(steps)) => steps,
+                Some(Err(_)) => return Err("--steps must be a number".into()),
+                None => 1,
+            };
+
+            let database = connect_db().await?;
+            let runs = rollback(&database, steps, dry_run).await?;
+
+            print_runs(&runs, "Rolled back", dry_run);
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_runs(runs: &[MigrationRun], action: &str, dry_run: bool) {
+    if runs.is_empty() {
+        println!("Nothing to do");
+    }
+
+    for run in runs {
+        match dry_run {
+            true => println!("Would run {} ({} documents)", run.name, run.documents),
+            false => println!("{} {} ({} documents)", action, run.name, run.documents),
+        }
+    }
+}
@@ -1,14 +1,139 @@
-use std::env;
+use std::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
 
-use mongodb::{options::ClientOptions, Client, Database};
+use mongodb::{bson::doc, Client, Database};
 
+use super::{
+    config::DatabaseConfig,
+    indexes::apply_indexes,
+    migrations::{has_pending_migrations, migrate, MigrationError},
+};
+
+/// The process-wide client. A warm Lambda reuses it, and its pool, across
+/// invocations instead of connecting on every request.
+static CLIENT: RwLock<Option<CachedClient>> = RwLock::new(None);
+
+#[derive(Clone)]
+struct CachedClient {
+    client: Client,
+    database_name: String,
+    health_check_interval: Duration,
+    checked_at: Instant,
+    /// Bumped on every rebuild.
+    generation: u64,
+}
+
+/// Builds the shared client and brings the indexes and migrations up to
+/// date. Call it once from `main` so a bad connection string fails the cold
+/// start rather than the first request. Failing to apply either is logged,
+/// not fatal.
+pub async fn init_db() -> mongodb::error::Result<()> {
+    let cached_client = build_client().await?;
+    let database = cached_client.client.database(&cached_client.database_name);
+
+    if let Err(error) = apply_indexes(&database, false).await {
+        eprintln!("Failed to apply database indexes: {}", error);
+    }
+
+    run_pending_migrations(&database).await;
+
+    Ok(())
+}
+
+/// Runs pending migrations under the migrations lock. Checking first keeps
+/// warm deploys from taking the lock when there is nothing to do, and a cold
+/// start that finds the lock taken leaves the work to the one holding it.
+async fn run_pending_migrations(database: &Database) {
+    match has_pending_migrations(database).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(error) => {
+            eprintln!("Failed to read migration status: {}", error);
+            return;
+        }
+    }
+
+    match migrate(database, None, false).await {
+        Ok(runs) => {
+            for run in runs {
+                eprintln!(
+                    "Applied migration {} ({} documents)",
+                    run.name, run.documents
+                );
+            }
+        }
+        Err(error @ MigrationError::Locked { .. }) => eprintln!("Skipped migrations: {}", error),
+        Err(error) => eprintln!("Failed to apply migrations: {}", error),
+    }
+}
+
+/// The database on the shared client, built on first use if `init_db` was
+/// not called. A client not used for a while is pinged first and rebuilt if
+/// the ping fails, e.g. after the Lambda was frozen and its sockets died.
 pub async fn connect_db() -> mongodb::error::Result<Database> {
-    let database_uri = env::var("DATABASE_URI").unwrap_or_default();
-    let database_name = env::var("DATABASE_NAME").unwrap_or_default();
+    let cached_client = CLIENT.read().ok().and_then(|client| client.clone());
+
+    let cached_client = match cached_client {
+        Some(cached_client)
+            if cached_client.checked_at.elapsed() < cached_client.health_check_interval =>
+        {
+            cached_client
+        }
+        Some(cached_client) if is_healthy(&cached_client).await => {
+            mark_checked(cached_client.generation);
+            cached_client
+        }
+        Some(_) => {
+            eprintln!("Database client failed its health check, reconnecting");
+            build_client().await?
+        }
+        None => build_client().await?,
+    };
+
+    Ok(cached_client.client.database(&cached_client.database_name))
+}
+
+async fn build_client() -> mongodb::error::Result<CachedClient> {
+    let config = DatabaseConfig::from_env();
+    let client = Client::with_options(config.client_options().await?)?;
 
-    let client_options = ClientOptions::parse(database_uri).await?;
-    let client = Client::with_options(client_options)?;
-    let database = client.database(&database_name);
+    let mut cached_client = CachedClient {
+        client,
+        database_name: config.name.clone(),
+        health_check_interval: config.health_check_interval(),
+        checked_at: Instant::now(),
+        generation: 0,
+    };
+
+    if let Ok(mut client) = CLIENT.write() {
+        cached_client.generation = client
+            .as_ref()
+            .map_or(0, |previous_client| previous_client.generation + 1);
+        *client = Some(cached_client.clone());
+    }
+
+    Ok(cached_client)
+}
+
+async fn is_healthy(cached_client: &CachedClient) -> bool {
+    cached_client
+        .client
+        .database("admin")
+        .run_command(doc! {"ping": 1}, None)
+        .await
+        .is_ok()
+}
 
-    Ok(database)
+fn mark_checked(generation: u64) {
+    if let Ok(mut client) = CLIENT.write() {
+        // Skip if another request swapped the client in the meantime.
+        if let Some(cached_client) = client
+            .as_mut()
+            .filter(|cached_client| cached_client.generation == generation)
+        {
+            cached_client.checked_at = Instant::now();
+        }
+    }
 }
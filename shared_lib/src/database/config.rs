@@ -0,0 +1,98 @@
+use std::{env, time::Duration};
+
+use mongodb::options::ClientOptions;
+
+/// Connection settings for the shared client. Each one comes from its env
+/// var, then from the connection string, then from the defaults below,
+/// which are tighter than the driver's so a Lambda fails before it times out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseConfig {
+    pub uri: String,
+    pub name: String,
+    pub max_pool_size: Option<u32>,
+    pub min_pool_size: Option<u32>,
+    pub connect_timeout: Option<Duration>,
+    pub server_selection_timeout: Option<Duration>,
+    pub max_idle_time: Option<Duration>,
+    /// How long a client is trusted before the next request pings it.
+    pub health_check_interval: Option<Duration>,
+}
+
+const DEFAULT_MAX_POOL_SIZE: u32 = 10;
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_SERVER_SELECTION_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+impl DatabaseConfig {
+    pub fn from_env() -> Self {
+        let number = |name: &str| env::var(name).ok()?.trim().parse::<u64>().ok();
+        let millis = |name: &str| number(name).map(Duration::from_millis);
+
+        Self {
+            uri: env::var("DATABASE_URI").unwrap_or_default(),
+            name: env::var("DATABASE_NAME").unwrap_or_default(),
+            max_pool_size: number("DATABASE_MAX_POOL_SIZE").map(|size| size as u32),
+            min_pool_size: number("DATABASE_MIN_POOL_SIZE").map(|size| size as u32),
+            connect_timeout: millis("DATABASE_CONNECT_TIMEOUT_MS"),
+            server_selection_timeout: millis("DATABASE_SERVER_SELECTION_TIMEOUT_MS"),
+            max_idle_time: millis("DATABASE_MAX_IDLE_TIME_MS"),
+            health_check_interval: number("DATABASE_HEALTH_CHECK_INTERVAL_SECONDS")
+                .map(Duration::from_secs),
+        }
+    }
+
+    pub async fn client_options(&self) -> mongodb::error::Result<ClientOptions> {
+        let mut client_options = ClientOptions::parse(&self.uri).await?;
+        self.apply(&mut client_options);
+
+        Ok(client_options)
+    }
+
+    fn apply(&self, client_options: &mut ClientOptions) {
+        client_options.max_pool_size = self
+            .max_pool_size
+            .or(client_options.max_pool_size)
+            .or(Some(DEFAULT_MAX_POOL_SIZE));
+        client_options.min_pool_size = self.min_pool_size.or(client_options.min_pool_size);
+        client_options.connect_timeout = self
+            .connect_timeout
+            .or(client_options.connect_timeout)
+            .or(Some(DEFAULT_CONNECT_TIMEOUT));
+        client_options.server_selection_timeout = self
+            .server_selection_timeout
+            .or(client_options.server_selection_timeout)
+            .or(Some(DEFAULT_SERVER_SELECTION_TIMEOUT));
+        client_options.max_idle_time = self.max_idle_time.or(client_options.max_idle_time);
+    }
+
+    pub fn health_check_interval(&self) -> Duration {
+        self.health_check_interval
+            .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_settings_win_over_the_uri_and_defaults_fill_the_rest() {
+        let config = DatabaseConfig {
+            max_pool_size: Some(3),
+            ..Default::default()
+        };
+
+        let mut client_options = ClientOptions::default();
+        client_options.max_pool_size = Some(50);
+        client_options.connect_timeout = Some(Duration::from_secs(1));
+        config.apply(&mut client_options);
+
+        assert_eq!(client_options.max_pool_size, Some(3));
+        assert_eq!(client_options.connect_timeout, Some(Duration::from_secs(1)));
+        assert_eq!(
+            client_options.server_selection_timeout,
+            Some(DEFAULT_SERVER_SELECTION_TIMEOUT)
+        );
+        assert_eq!(client_options.min_pool_size, None);
+    }
+}
@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use mongodb::{
+    bson::{doc, to_bson, Document},
+    options::{IndexOptions, UpdateOptions},
+    Database, IndexModel,
+};
+
+use crate::{traits::model_traits::ModelTraits, utils::token::hash_token};
+
+/// Collection recording which schema changes a database already has.
+pub const SCHEMA_COLLECTION: &str = "_schema";
+
+const INDEXES_SCHEMA_ID: &str = "indexes";
+
+/// The indexes one model declares for its collection.
+#[derive(Debug, Clone)]
+pub struct CollectionIndexes {
+    pub collection: String,
+    pub indexes: Vec<IndexModel>,
+}
+
+impl CollectionIndexes {
+    pub fn of<M: ModelTraits>() -> Self {
+        Self {
+            collection: M::get_struct_name_as_plural_string(),
+            indexes: M::indexes(),
+        }
+    }
+}
+
+/// Submitted by `#[derive(Model)]` for every model, so none can be left out
+/// of [`registry`].
+pub struct RegisteredModel(pub fn() -> CollectionIndexes);
+
+inventory::collect!(RegisteredModel);
+
+/// Every model deriving `Model`, ordered by collection name.
+pub fn registry() -> Vec<CollectionIndexes> {
+    let mut registry = inventory::iter::<RegisteredModel>
+        .into_iter()
+        .map(|RegisteredModel(indexes)| indexes())
+        .collect::<Vec<_>>();
+
+    registry.sort_by(|a, b| a.collection.cmp(&b.collection));
+    registry
+}
+
+/// A hash of every registered index spec. Adding or changing an index in
+/// any model changes it, so the next cold start applies the indexes.
+pub fn indexes_version() -> String {
+    specs_hash(&registry())
+}
+
+fn specs_hash(registry: &[CollectionIndexes]) -> String {
+    let specs = registry
+        .iter()
+        .map(|collection_indexes| {
+            doc! {
+                "collection": &collection_indexes.collection,
+                "indexes": to_bson(&collection_indexes.indexes).unwrap_or_default(),
+            }
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    hash_token(&specs)
+}
+
+pub fn unique_index(field: &str) -> IndexModel {
+    IndexModel::builder()
+        .keys(doc! {field: 1})
+        .options(IndexOptions::builder().unique(true).build())
+        .build()
+}
+
+pub fn compound_index(keys: Document) -> IndexModel {
+    IndexModel::builder().keys(keys).build()
+}
+
+pub fn text_index(fields: &[&str]) -> IndexModel {
+    let keys = fields
+        .iter()
+        .map(|field| (field.to_string(), "text".into()))
+        .collect::<Document>();
+
+    IndexModel::builder().keys(keys).build()
+}
+
+/// Documents are removed `expire_after` past the date in `field`. The field
+/// has to hold a BSON date; MongoDB never expires documents whose field is
+/// a string.
+pub fn ttl_index(field: &str, expire_after: Duration) -> IndexModel {
+    IndexModel::builder()
+        .keys(doc! {field: 1})
+        .options(IndexOptions::builder().expire_after(expire_after).build())
+        .build()
+}
+
+/// The index version recorded in `_schema`, if any.
+pub async fn applied_indexes_version(
+    database: &Database,
+) -> mongodb::error::Result<Option<String>> {
+    let schema = database
+        .collection::<Document>(SCHEMA_COLLECTION)
+        .find_one(doc! {"_id": INDEXES_SCHEMA_ID}, None)
+        .await?;
+
+    Ok(schema.and_then(|schema| schema.get_str("version").ok().map(str::to_owned)))
+}
+
+/// Creates every registered index unless `_schema` already records the
+/// current [`indexes_version`]. Creating an index that exists with the same spec is
+/// a no-op, so running this twice, or from two cold starts at once, is safe.
+/// Returns whether the indexes were applied.
+pub async fn apply_indexes(database: &Database, force: bool) -> mongodb::error::Result<bool> {
+    let version = indexes_version();
+    let applied_version = applied_indexes_version(database).await?;
+
+    if !force && applied_version.as_ref() == Some(&version) {
+        return Ok(false);
+    }
+
+    for collection_indexes in registry() {
+        if collection_indexes.indexes.is_empty() {
+            continue;
+        }
+
+        database
+            .collection::<Document>(&collection_indexes.collection)
+            .create_indexes(collection_indexes.indexes, None)
+            .await?;
+    }
+
+    database
+        .collection::<Document>(SCHEMA_COLLECTION)
+        .update_one(
+            doc! {"_id": INDEXES_SCHEMA_ID},
+            doc! {"$set": {
+                "version": version,
+                "applied_at": to_bson(&Utc::now()).unwrap_or_default(),
+            }},
+            UpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_keep_their_collection_names() {
+        let collections = registry()
+            .into_iter()
+            .map(|collection_indexes| collection_indexes.collection)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            collections,
+            [
+                "apikeys",
+                "audit_events",
+                "impersonationsessions",
+                "invitations",
+                "posts",
+                "refreshtokens",
+                "users",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_version_changes_with_any_index_spec() {
+        let mut registry = registry();
+        let version = specs_hash(&registry);
+
+        assert_eq!(version, indexes_version());
+
+        let users = registry
+            .iter_mut()
+            .find(|collection_indexes| collection_indexes.collection == "users")
+            .unwrap();
+        users.indexes[0] = compound_index(users.indexes[0].keys.clone());
+
+        assert_ne!(specs_hash(&registry), version);
+    }
+
+    #[test]
+    fn every_model_declares_its_unique_fields() {
+        let unique_fields = registry()
+            .into_iter()
+            .flat_map(|collection_indexes| {
+                collection_indexes
+                    .indexes
+                    .into_iter()
+                    .filter(|index| index.options.as_ref().and_then(|o| o.unique) == Some(true))
+                    .flat_map(move |index| {
+                        let collection = collection_indexes.collection.clone();
+
+                        index
+                            .keys
+                            .keys()
+                            .map(move |field| format!("{}.{}", collection, field))
+                            .collect::<Vec<_>>()
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        for field in [
+            "users.email",
+            "users.username",
+            "posts.slug",
+            "posts.title",
+            "refreshtokens.token_hash",
+            "apikeys.prefix",
+            "invitations.token_hash",
+        ] {
+            assert!(unique_fields.contains(&field.to_owned()), "{}", field);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Users created before email verification existed have no
+//! `email_verified_at` at all, so with `REQUIRE_EMAIL_VERIFICATION` on they
+//! could no longer sign in. Treat them as verified from when they signed up.
+//! Users created since store an explicit null and are left alone.
+
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use mongodb::{
+    bson::{doc, to_bson, Document},
+    Database,
+};
+
+use crate::{models::user::User, traits::model_traits::ModelTraits};
+
+use super::Migration;
+
+pub fn migration() -> Migration {
+    Migration {
+        name: "0002_backfill_email_verified_at",
+        up,
+        down,
+    }
+}
+
+fn up(database: &Database, dry_run: bool) -> BoxFuture<'_, mongodb::error::Result<u64>> {
+    Box::pin(async move {
+        let users = database.collection::<Document>(&User::get_struct_name_as_plural_string());
+        let filter = doc! {"email_verified_at": {"$exists": false}};
+
+        if dry_run {
+            return users.count_documents(filter, None).await;
+        }
+
+        let now = to_bson(&Utc::now()).unwrap_or_default();
+
+        let update_result = users
+            .update_many(
+                filter,
+                vec![doc! {"$set": {
+                    "email_verified_at": {"$ifNull": ["$created_at", now]},
+                }}],
+                None,
+            )
+            .await?;
+
+        Ok(update_result.modified_count)
+    })
+}
+
+/// Does nothing. The backfilled users cannot be told apart from ones who
+/// verified, and unsetting theirs would lock them out again.
+fn down(_database: &Database, _dry_run: bool) -> BoxFuture<'_, mongodb::error::Result<u64>> {
+    Box::pin(async { Ok(0) })
+}
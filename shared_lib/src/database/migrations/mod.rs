@@ -0,0 +1,342 @@
+mod m0002_backfill_email_verified_at;
+
+use std::{env, fmt};
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{future::BoxFuture, stream::StreamExt};
+use mongodb::{
+    bson::{doc, to_bson, DateTime as BsonDateTime, Document},
+    options::FindOptions,
+    Database,
+};
+use serde::{Deserialize, Serialize};
+
+use super::indexes::SCHEMA_COLLECTION;
+
+/// Collection listing the migrations a database has applied, one document each.
+pub const MIGRATIONS_COLLECTION: &str = "_migrations";
+
+const LOCK_ID: &str = "migrations_lock";
+
+/// A lock older than this is taken to be left behind by a crashed run.
+const LOCK_TIMEOUT_MINUTES: i64 = 15;
+
+/// One direction of a migration. Returns how many documents it changed, or
+/// would change when `dry_run` is set, in which case it must not write.
+pub type MigrationStep =
+    for<'a> fn(&'a Database, bool) -> BoxFuture<'a, mongodb::error::Result<u64>>;
+
+pub struct Migration {
+    /// Sorts the migrations, so it starts with a zero padded number.
+    pub name: &'static str,
+    pub up: MigrationStep,
+    pub down: MigrationStep,
+}
+
+/// Every migration, oldest first. Add new ones at the end and never rename
+/// or reorder one that has shipped.
+pub fn migrations() -> Vec<Migration> {
+    vec![m0002_backfill_email_verified_at::migration()]
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AppliedMigration {
+    #[serde(rename = "_id")]
+    name: String,
+    applied_at: DateTime<Utc>,
+}
+
+/// A migration and when it was applied, if it was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub name: &'static str,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// What running one migration did, or would do in a dry run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationRun {
+    pub name: &'static str,
+    pub documents: u64,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Database(mongodb::error::Error),
+    /// Another run holds the lock.
+    Locked {
+        locked_by: String,
+        locked_at: String,
+    },
+    UnknownMigration(String),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Database(error) => write!(f, "database error: {}", error),
+            Self::Locked {
+                locked_by,
+                locked_at,
+            } => write!(
+                f,
+                "migrations are locked by {} since {}",
+                locked_by, locked_at
+            ),
+            Self::UnknownMigration(name) => write!(f, "no migration named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<mongodb::error::Error> for MigrationError {
+    fn from(error: mongodb::error::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+pub async fn migration_status(database: &Database) -> Result<Vec<MigrationStatus>, MigrationError> {
+    let applied_migrations = applied_migrations(database).await?;
+
+    Ok(migrations()
+        .into_iter()
+        .map(|migration| MigrationStatus {
+            name: migration.name,
+            applied_at: applied_migrations
+                .iter()
+                .find(|applied_migration| applied_migration.name == migration.name)
+                .map(|applied_migration| applied_migration.applied_at),
+        })
+        .collect())
+}
+
+/// Whether any declared migration has not been applied yet.
+pub async fn has_pending_migrations(database: &Database) -> Result<bool, MigrationError> {
+    Ok(migration_status(database)
+        .await?
+        .iter()
+        .any(|status| status.applied_at.is_none()))
+}
+
+/// Applies pending migrations in order, up to and including `target` when
+/// given. Stops at the first failure; the migrations before it stay applied.
+pub async fn migrate(
+    database: &Database,
+    target: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<MigrationRun>, MigrationError> {
+    let migrations = migrations();
+
+    if let Some(target) = target {
+        if !migrations.iter().any(|migration| migration.name == target) {
+            return Err(MigrationError::UnknownMigration(target.to_owned()));
+        }
+    }
+
+    with_lock(database, dry_run, async {
+        let applied_migrations = applied_migrations(database).await?;
+        let mut runs = Vec::new();
+
+        for migration in &migrations {
+            let is_applied = applied_migrations
+                .iter()
+                .any(|applied_migration| applied_migration.name == migration.name);
+
+            if !is_applied {
+                let documents = (migration.up)(database, dry_run).await?;
+
+                if !dry_run {
+                    record_applied(database, migration.name).await?;
+                }
+
+                runs.push(MigrationRun {
+                    name: migration.name,
+                    documents,
+                });
+            }
+
+            if target == Some(migration.name) {
+                break;
+            }
+        }
+
+        Ok(runs)
+    })
+    .await
+}
+
+/// Reverts the last `steps` applied migrations, newest first.
+pub async fn rollback(
+    database: &Database,
+    steps: usize,
+    dry_run: bool,
+) -> Result<Vec<MigrationRun>, MigrationError> {
+    let migrations = migrations();
+
+    with_lock(database, dry_run, async {
+        let applied_migrations = applied_migrations(database).await?;
+        let mut runs = Vec::new();
+
+        for applied_migration in applied_migrations.iter().rev().take(steps) {
+            let migration = migrations
+                .iter()
+                .find(|migration| migration.name == applied_migration.name)
+                .ok_or_else(|| MigrationError::UnknownMigration(applied_migration.name.clone()))?;
+
+            let documents = (migration.down)(database, dry_run).await?;
+
+            if !dry_run {
+                database
+                    .collection::<Document>(MIGRATIONS_COLLECTION)
+                    .delete_one(doc! {"_id": migration.name}, None)
+                    .await?;
+            }
+
+            runs.push(MigrationRun {
+                name: migration.name,
+                documents,
+            });
+        }
+
+        Ok(runs)
+    })
+    .await
+}
+
+/// Applied migrations, oldest first.
+async fn applied_migrations(database: &Database) -> Result<Vec<AppliedMigration>, MigrationError> {
+    let find_options = FindOptions::builder().sort(doc! {"_id": 1}).build();
+
+    let mut cursor = database
+        .collection::<AppliedMigration>(MIGRATIONS_COLLECTION)
+        .find(doc! {}, find_options)
+        .await?;
+
+    let mut applied_migrations = Vec::new();
+
+    while let Some(applied_migration) = cursor.next().await {
+        applied_migrations.push(applied_migration?);
+    }
+
+    Ok(applied_migrations)
+}
+
+async fn record_applied(database: &Database, name: &str) -> Result<(), MigrationError> {
+    database
+        .collection::<Document>(MIGRATIONS_COLLECTION)
+        .insert_one(
+            doc! {"_id": name, "applied_at": to_bson(&Utc::now()).unwrap_or_default()},
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Runs `run` holding the migrations lock, so two deploys cannot migrate at
+/// once. Dry runs only read and skip the lock.
+async fn with_lock<T>(
+    database: &Database,
+    dry_run: bool,
+    run: impl std::future::Future<Output = Result<T, MigrationError>>,
+) -> Result<T, MigrationError> {
+    if dry_run {
+        return run.await;
+    }
+
+    acquire_lock(database).await?;
+    let result = run.await;
+
+    // The run's outcome matters more. A lock left behind goes stale and is
+    // taken over after `LOCK_TIMEOUT_MINUTES`.
+    if let Err(error) = release_lock(database).await {
+        eprintln!("Failed to release the migrations lock: {}", error);
+    }
+
+    result
+}
+
+async fn acquire_lock(database: &Database) -> Result<(), MigrationError> {
+    let schema = database.collection::<Document>(SCHEMA_COLLECTION);
+    // Stored as a BSON date, so the stale check below compares dates.
+    let now = BsonDateTime::now();
+    let stale_before = BsonDateTime::from_millis(
+        now.timestamp_millis() - Duration::minutes(LOCK_TIMEOUT_MINUTES).num_milliseconds(),
+    );
+
+    let locked_by = format!(
+        "{}:{}",
+        env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_owned()),
+        std::process::id()
+    );
+
+    // Only a missing or stale lock matches, and the unique `_id` stops two
+    // runs from both inserting one.
+    let result = schema
+        .update_one(
+            doc! {
+                "_id": LOCK_ID,
+                "locked_at": {"$lt": stale_before},
+            },
+            doc! {"$set": {"locked_by": &locked_by, "locked_at": now}},
+            mongodb::options::UpdateOptions::builder()
+                .upsert(true)
+                .build(),
+        )
+        .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) if is_duplicate_key(&error) => {
+            let lock = schema.find_one(doc! {"_id": LOCK_ID}, None).await?;
+            let lock = lock.unwrap_or_default();
+
+            Err(MigrationError::Locked {
+                locked_by: lock.get_str("locked_by").unwrap_or_default().to_owned(),
+                locked_at: lock
+                    .get_datetime("locked_at")
+                    .ok()
+                    .and_then(|locked_at| locked_at.try_to_rfc3339_string().ok())
+                    .unwrap_or_default(),
+            })
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+async fn release_lock(database: &Database) -> Result<(), MigrationError> {
+    database
+        .collection::<Document>(SCHEMA_COLLECTION)
+        .delete_one(doc! {"_id": LOCK_ID}, None)
+        .await?;
+
+    Ok(())
+}
+
+fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(
+        error.kind.as_ref(),
+        mongodb::error::ErrorKind::Write(mongodb::error::WriteFailure::WriteError(error))
+            if error.code == 11000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_uniquely_named_in_order() {
+        let names = migrations()
+            .iter()
+            .map(|migration| migration.name)
+            .collect::<Vec<_>>();
+
+        let mut sorted_names = names.clone();
+        sorted_names.sort();
+        sorted_names.dedup();
+
+        assert_eq!(names, sorted_names);
+    }
+}
@@ -1 +1,5 @@
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod config;
+pub mod indexes;
+pub mod migrations;
+pub mod queries;
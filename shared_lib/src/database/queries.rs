@@ -0,0 +1,221 @@
+//! The queries behind every `#[derive(Model)]` implementation of
+//! [`ModelTraits`], written once so the models cannot drift apart.
+
+use chrono::Utc;
+use futures_util::stream::StreamExt;
+use mongodb::{
+    bson::{doc, from_document, to_bson, Document},
+    options::FindOptions,
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Database,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use validator::Validate;
+
+use crate::{
+    traits::model_traits::ModelTraits, DataInsertError, PaginatedData, PaginationMetadata,
+};
+
+const DEFAULT_ITEMS_PER_PAGE: i64 = 10;
+
+pub async fn insert<M>(database: &Database, model: &M) -> Result<InsertOneResult, DataInsertError>
+where
+    M: ModelTraits + Validate + Serialize + Send + Sync,
+{
+    model.validate()?;
+
+    let database_insert_response = database
+        .collection::<M>(&M::get_struct_name_as_plural_string())
+        .insert_one(model, None)
+        .await?;
+
+    Ok(database_insert_response)
+}
+
+pub async fn find<M: ModelTraits, T: DeserializeOwned>(
+    database: &Database,
+    filter: Document,
+    projection: Option<Document>,
+    sort: Option<Document>,
+    limit: i64,
+) -> mongodb::error::Result<Vec<T>> {
+    let find_options = FindOptions::builder()
+        .projection(projection)
+        .sort(sort)
+        .limit(limit)
+        .build();
+
+    let mut database_find_cursor = database
+        .collection::<Document>(&M::get_struct_name_as_plural_string())
+        .find(filter, find_options)
+        .await?;
+
+    let mut documents = Vec::new();
+
+    while let Some(document) = database_find_cursor.next().await {
+        documents.push(from_document::<T>(document?)?);
+    }
+
+    Ok(documents)
+}
+
+/// Pages start at 1. Pages and page sizes below 1 are read as 1, and the
+/// page size defaults to 10. An empty page comes back without metadata.
+pub async fn find_paginated<M: ModelTraits, T: DeserializeOwned>(
+    database: &Database,
+    filter: Document,
+    projection: Option<Document>,
+    sort: Option<Document>,
+    current_page: Option<i64>,
+    items_per_page: Option<i64>,
+) -> mongodb::error::Result<PaginatedData<T>> {
+    let current_page = current_page.unwrap_or(1).max(1);
+    let items_per_page = items_per_page.unwrap_or(DEFAULT_ITEMS_PER_PAGE).max(1);
+
+    let total_items = count_documents::<M>(database, filter.clone()).await?;
+    let total_pages = (total_items as f64 / items_per_page as f64).ceil() as u64;
+
+    let find_options = FindOptions::builder()
+        .projection(projection)
+        .sort(sort)
+        .limit(items_per_page)
+        .skip((current_page as u64 - 1) * items_per_page as u64)
+        .build();
+
+    let mut database_find_cursor = database
+        .collection::<Document>(&M::get_struct_name_as_plural_string())
+        .find(filter, find_options)
+        .await?;
+
+    let mut paginated_data = PaginatedData {
+        documents: Vec::new(),
+        metadata: PaginationMetadata::default(),
+    };
+
+    while let Some(document) = database_find_cursor.next().await {
+        paginated_data
+            .documents
+            .push(from_document::<T>(document?)?);
+    }
+
+    if paginated_data.documents.is_empty() {
+        return Ok(paginated_data);
+    }
+
+    paginated_data.metadata = PaginationMetadata {
+        current_page: Some(current_page as u64),
+        total_pages: Some(total_pages),
+        total_items: Some(total_items),
+        items_per_page: Some(items_per_page as u64),
+    };
+
+    Ok(paginated_data)
+}
+
+pub async fn update_one<M: ModelTraits>(
+    database: &Database,
+    filter: Document,
+    update: Document,
+) -> mongodb::error::Result<UpdateResult> {
+    database
+        .collection::<Document>(&M::get_struct_name_as_plural_string())
+        .update_one(filter, update, None)
+        .await
+}
+
+pub async fn update_many<M: ModelTraits>(
+    database: &Database,
+    filter: Document,
+    update: Document,
+) -> mongodb::error::Result<UpdateResult> {
+    database
+        .collection::<Document>(&M::get_struct_name_as_plural_string())
+        .update_many(filter, update, None)
+        .await
+}
+
+pub async fn delete_one<M: ModelTraits>(
+    database: &Database,
+    filter: Document,
+) -> mongodb::error::Result<DeleteResult> {
+    database
+        .collection::<Document>(&M::get_struct_name_as_plural_string())
+        .delete_one(filter, None)
+        .await
+}
+
+pub async fn count_documents<M: ModelTraits>(
+    database: &Database,
+    filter: Document,
+) -> mongodb::error::Result<u64> {
+    database
+        .collection::<Document>(&M::get_struct_name_as_plural_string())
+        .count_documents(filter, None)
+        .await
+}
+
+/// Adds `updated_at` to the `$set` of an update, for models declared with
+/// `#[model(timestamps)]`. An update that already touches `updated_at`
+/// through any operator is left alone.
+pub fn touch(mut update: Document) -> Document {
+    let touches_updated_at = update
+        .iter()
+        .filter(|(operator, _)| operator.starts_with('$'))
+        .any(|(_, fields)| {
+            fields
+                .as_document()
+                .is_some_and(|fields| fields.contains_key("updated_at"))
+        });
+
+    if touches_updated_at {
+        return update;
+    }
+
+    let now = to_bson(&Utc::now()).unwrap_or_default();
+
+    match update.get_document_mut("$set") {
+        Ok(set) => {
+            set.insert("updated_at", now);
+        }
+        Err(_) => {
+            update.insert("$set", doc! {"updated_at": now});
+        }
+    }
+
+    update
+}
+
+/// What updates and deletes on an `#[model(append_only)]` model return.
+pub fn append_only_error(collection: &str) -> mongodb::error::Error {
+    mongodb::error::Error::custom(format!("The {} collection is append-only", collection))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touch_adds_updated_at_to_the_set() {
+        let update = touch(doc! {"$set": {"name": "new"}, "$unset": {"revoked_at": ""}});
+
+        let set = update.get_document("$set").unwrap();
+        assert_eq!(set.get_str("name"), Ok("new"));
+        assert!(set.contains_key("updated_at"));
+        assert!(update.contains_key("$unset"));
+
+        let update = touch(doc! {"$inc": {"uses": 1}});
+        assert!(update
+            .get_document("$set")
+            .unwrap()
+            .contains_key("updated_at"));
+    }
+
+    #[test]
+    fn touch_keeps_an_explicit_updated_at() {
+        let update = doc! {"$set": {"updated_at": "2024-01-01T00:00:00Z"}};
+        assert_eq!(touch(update.clone()), update);
+
+        let update = doc! {"$unset": {"updated_at": ""}};
+        assert_eq!(touch(update.clone()), update);
+    }
+}
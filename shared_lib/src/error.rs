@@ -0,0 +1,366 @@
+use std::fmt;
+
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use lambda_runtime::Error;
+use mongodb::bson::{Bson, Document};
+use serde_json::json;
+
+use crate::{
+    router::extract::Rejection, utils::password_hasher::PasswordHashError, AppErrorResponse,
+    DataInsertError,
+};
+
+/// Everything a handler can fail with. Each variant maps to one status and a
+/// stable `code` that clients can match on instead of the message.
+#[derive(Debug)]
+pub enum AppError {
+    Validation(validator::ValidationErrors),
+    Duplicate(DuplicateKey),
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    MethodNotAllowed,
+    Conflict(String),
+    Rejected(Rejection),
+    Database(mongodb::error::Error),
+    Serialization(String),
+    PasswordHash(PasswordHashError),
+    Upstream(reqwest::Error),
+    /// The action went through but its email could not be sent. The message
+    /// tells the client what did happen.
+    EmailNotSent(String),
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Validation(_) | Self::Duplicate(_) | Self::BadRequest(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Rejected(rejection) => rejection.status,
+            Self::Upstream(_) => StatusCode::BAD_GATEWAY,
+            Self::Database(_)
+            | Self::Serialization(_)
+            | Self::PasswordHash(_)
+            | Self::EmailNotSent(_)
+            | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Validation(_) => "validation_failed",
+            Self::Duplicate(_) => "already_exists",
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::MethodNotAllowed => "method_not_allowed",
+            Self::Conflict(_) => "conflict",
+            Self::Rejected(rejection) => rejection.code,
+            Self::Database(_) => "database_error",
+            Self::Serialization(_) => "serialization_error",
+            Self::PasswordHash(_) => "password_hash_error",
+            Self::Upstream(_) => "upstream_error",
+            Self::EmailNotSent(_) => "email_not_sent",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    /// The message sent to the client. Server errors keep their details in
+    /// the logs only.
+    pub fn message(&self) -> String {
+        match self {
+            Self::Validation(_) => "Validation failed".to_owned(),
+            Self::Duplicate(duplicate_key) => match &duplicate_key.value {
+                Some(value) => format!("An error occured. {} already exists", value),
+                None => "An error occured. A record with these details already exists".to_owned(),
+            },
+            Self::BadRequest(message)
+            | Self::Unauthorized(message)
+            | Self::Forbidden(message)
+            | Self::NotFound(message)
+            | Self::Conflict(message)
+            | Self::EmailNotSent(message) => message.clone(),
+            Self::MethodNotAllowed => "Method not allowed".to_owned(),
+            Self::Rejected(rejection) => rejection.message.clone(),
+            Self::Upstream(_) => "An upstream service failed".to_owned(),
+            Self::Database(_)
+            | Self::Serialization(_)
+            | Self::PasswordHash(_)
+            | Self::Internal(_) => "An error occured".to_owned(),
+        }
+    }
+
+    /// Turns a handler's boxed error back into an `AppError`. Errors of
+    /// unknown types become `Internal`.
+    pub fn from_boxed(error: Error) -> Self {
+        let error = match error.downcast::<AppError>() {
+            Ok(app_error) => return *app_error,
+            Err(error) => error,
+        };
+
+        let error = match error.downcast::<Rejection>() {
+            Ok(rejection) => return Self::Rejected(*rejection),
+            Err(error) => error,
+        };
+
+        let error = match error.downcast::<mongodb::error::Error>() {
+            Ok(mongo_error) => return Self::from(*mongo_error),
+            Err(error) => error,
+        };
+
+        let error = match error.downcast::<reqwest::Error>() {
+            Ok(reqwest_error) => return Self::from(*reqwest_error),
+            Err(error) => error,
+        };
+
+        Self::Internal(error.to_string())
+    }
+
+    pub fn into_response(self) -> Result<ApiGatewayProxyResponse, Error> {
+        let status = self.status();
+
+        if status.is_server_error() {
+            eprintln!("{}: {}", self.code(), self);
+        }
+
+        let data = match &self {
+            Self::Validation(errors) => json!({"code": self.code(), "errors": errors}),
+            Self::Duplicate(duplicate_key) => {
+                json!({"code": self.code(), "field": duplicate_key.field})
+            }
+            _ => json!({"code": self.code()}),
+        };
+
+        AppErrorResponse::new(status, Some(self.message()), Some(data))
+    }
+}
+
+/// The field and value that collided with a unique index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    pub field: Option<String>,
+    pub value: Option<String>,
+    /// The server's message, kept for the logs.
+    pub message: String,
+}
+
+impl DuplicateKey {
+    /// Reads the key from the server's `keyValue` details when it sends them,
+    /// and from the message text otherwise.
+    pub fn from_write_error(error: &mongodb::error::WriteError) -> Self {
+        let (field, value) = error
+            .details
+            .as_ref()
+            .and_then(key_from_details)
+            .or_else(|| key_from_message(&error.message))
+            .unzip();
+
+        Self {
+            field: field.flatten(),
+            value: value.flatten(),
+            message: error.message.clone(),
+        }
+    }
+}
+
+fn key_from_details(details: &Document) -> Option<(Option<String>, Option<String>)> {
+    let (field, value) = details.get_document("keyValue").ok()?.iter().next()?;
+
+    let value = match value {
+        Bson::String(value) => value.clone(),
+        value => value.to_string(),
+    };
+
+    Some((Some(field.clone()), Some(value)))
+}
+
+/// Parses a message such as
+/// `E11000 duplicate key error collection: db.users index: email_1 dup key: { email: "a@b.c" }`.
+/// Older servers leave the field out of `dup key`, so the index name stands in for it.
+fn key_from_message(message: &str) -> Option<(Option<String>, Option<String>)> {
+    let (_, key) = message.split_once("dup key: ")?;
+    let key = key
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .trim();
+    let (field, value) = key.split_once(": ")?;
+
+    // A compound key lists every field; the first one is enough to point at.
+    let value = value
+        .split(", ")
+        .next()
+        .unwrap_or(value)
+        .trim()
+        .trim_matches('"');
+
+    let field = match field.trim() {
+        "" => message
+            .split_once("index: ")
+            .and_then(|(_, index)| index.split_whitespace().next())
+            .and_then(|index| index.rsplit(['$', '.']).next())
+            .and_then(|index| index.rsplit_once('_'))
+            .map(|(field, _)| field),
+        field => Some(field),
+    };
+
+    Some((
+        field.filter(|field| !field.is_empty()).map(str::to_owned),
+        Some(value.to_owned()),
+    ))
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validation(errors) => write!(f, "validation failed: {}", errors),
+            Self::Duplicate(duplicate_key) => write!(f, "duplicate key: {}", duplicate_key.message),
+            Self::Database(error) => write!(f, "database error: {}", error),
+            Self::Serialization(error) => write!(f, "serialization error: {}", error),
+            Self::PasswordHash(error) => write!(f, "{}", error),
+            Self::Upstream(error) => write!(f, "upstream error: {}", error),
+            Self::Internal(error) => write!(f, "internal error: {}", error),
+            _ => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<DataInsertError> for AppError {
+    fn from(error: DataInsertError) -> Self {
+        match error {
+            DataInsertError::FieldValidationError(errors) => Self::Validation(errors),
+            DataInsertError::MongoDuplicateError(error) => Self::Duplicate(error),
+            DataInsertError::MongoWriteError(error) => {
+                Self::Internal(format!("write error {}: {}", error.code, error.message))
+            }
+            DataInsertError::OtherMongoError(error) => Self::Database(error),
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for AppError {
+    fn from(error: mongodb::error::Error) -> Self {
+        Self::from(DataInsertError::from(error))
+    }
+}
+
+impl From<validator::ValidationErrors> for AppError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        Self::Validation(errors)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Serialization(error.to_string())
+    }
+}
+
+impl From<mongodb::bson::de::Error> for AppError {
+    fn from(error: mongodb::bson::de::Error) -> Self {
+        Self::Serialization(error.to_string())
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for AppError {
+    fn from(error: mongodb::bson::ser::Error) -> Self {
+        Self::Serialization(error.to_string())
+    }
+}
+
+impl From<bcrypt::BcryptError> for AppError {
+    fn from(error: bcrypt::BcryptError) -> Self {
+        Self::PasswordHash(PasswordHashError::from(error))
+    }
+}
+
+impl From<PasswordHashError> for AppError {
+    fn from(error: PasswordHashError) -> Self {
+        Self::PasswordHash(error)
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Upstream(error)
+    }
+}
+
+impl From<Rejection> for AppError {
+    fn from(rejection: Rejection) -> Self {
+        Self::Rejected(rejection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_error(message: &str) -> mongodb::error::WriteError {
+        serde_json::from_value(json!({"code": 11000, "errmsg": message})).unwrap()
+    }
+
+    #[test]
+    fn duplicate_key_is_read_from_details_then_message() {
+        let mut error = write_error("E11000 duplicate key error");
+        error.details = Some(mongodb::bson::doc! {
+            "keyPattern": {"slug": 1},
+            "keyValue": {"slug": "hello-world"},
+        });
+
+        let duplicate_key = DuplicateKey::from_write_error(&error);
+        assert_eq!(duplicate_key.field.as_deref(), Some("slug"));
+        assert_eq!(duplicate_key.value.as_deref(), Some("hello-world"));
+
+        let duplicate_key = DuplicateKey::from_write_error(&write_error(
+            r#"E11000 duplicate key error collection: db.users index: email_1 dup key: { email: "a@b.c" }"#,
+        ));
+        assert_eq!(duplicate_key.field.as_deref(), Some("email"));
+        assert_eq!(duplicate_key.value.as_deref(), Some("a@b.c"));
+
+        let duplicate_key = DuplicateKey::from_write_error(&write_error(
+            r#"E11000 duplicate key error index: db.users.$username_1 dup key: { : "ferris" }"#,
+        ));
+        assert_eq!(duplicate_key.field.as_deref(), Some("username"));
+        assert_eq!(duplicate_key.value.as_deref(), Some("ferris"));
+
+        let duplicate_key = DuplicateKey::from_write_error(&write_error("E11000"));
+        assert_eq!((duplicate_key.field, duplicate_key.value), (None, None));
+    }
+
+    #[test]
+    fn errors_map_to_status_and_code() {
+        let error = AppError::from(DataInsertError::MongoDuplicateError(
+            DuplicateKey::from_write_error(&write_error("E11000")),
+        ));
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.code(), "already_exists");
+
+        let error = AppError::from_boxed("boom".into());
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.message(), "An error occured");
+
+        let error = AppError::from_boxed(AppError::NotFound("Post not found".to_owned()).into());
+        assert_eq!(error.code(), "not_found");
+        assert_eq!(error.message(), "Post not found");
+
+        let reqwest_error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let error = AppError::from_boxed(reqwest_error.into());
+        assert_eq!(error.status(), StatusCode::BAD_GATEWAY);
+
+        let error = AppError::EmailNotSent("Invitation created".to_owned());
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.message(), "Invitation created");
+    }
+}
@@ -0,0 +1,94 @@
+use aws_lambda_events::http::StatusCode;
+use lambda_runtime::LambdaEvent;
+use tower::Layer;
+
+use crate::{
+    router::{
+        extract::{Rejection, MAX_BODY_BYTES},
+        HandlerFuture,
+    },
+    RequestPayload,
+};
+
+use super::{LambdaService, Middleware, Wrap};
+
+/// Answers 413 for bodies over `max_bytes` before any handler runs.
+/// Base64 bodies are measured by their decoded size.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimitLayer {
+    max_bytes: usize,
+}
+
+impl BodyLimitLayer {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes }
+    }
+}
+
+impl Default for BodyLimitLayer {
+    fn default() -> Self {
+        Self::new(MAX_BODY_BYTES)
+    }
+}
+
+impl<S> Layer<S> for BodyLimitLayer {
+    type Service = Middleware<Self, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Middleware::new(*self, inner)
+    }
+}
+
+impl Wrap for BodyLimitLayer {
+    fn wrap<S: LambdaService>(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+        mut inner: S,
+    ) -> HandlerFuture {
+        let body_bytes = body_bytes(&event.payload);
+
+        if body_bytes > self.max_bytes {
+            let rejection = Rejection::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "body_too_large",
+                format!("Request body cannot be more than {} bytes", self.max_bytes),
+            );
+
+            return Box::pin(async move { rejection.into_response() });
+        }
+
+        Box::pin(inner.call(event))
+    }
+}
+
+fn body_bytes(payload: &RequestPayload) -> usize {
+    let body = payload.body.as_deref().unwrap_or_default().trim();
+
+    match payload.is_base64_encoded {
+        true => (body.len() / 4 * 3)
+            .saturating_sub(body.bytes().rev().take_while(|byte| *byte == b'=').count()),
+        false => body.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_bodies_are_measured_decoded() {
+        let payload = RequestPayload {
+            body: Some("eyJuYW1lIjoiY3JhYiJ9".to_owned()),
+            is_base64_encoded: true,
+            ..Default::default()
+        };
+        assert_eq!(body_bytes(&payload), r#"{"name":"crab"}"#.len());
+
+        let payload = RequestPayload {
+            body: Some("aGk=".to_owned()),
+            is_base64_encoded: true,
+            ..Default::default()
+        };
+        assert_eq!(body_bytes(&payload), 2);
+    }
+}
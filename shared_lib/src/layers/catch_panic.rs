@@ -0,0 +1,59 @@
+use std::{
+    any::Any,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use futures_util::FutureExt;
+use lambda_runtime::{Error, LambdaEvent};
+use tower::Layer;
+
+use crate::{error::AppError, router::HandlerFuture, RequestPayload};
+
+use super::{LambdaService, Middleware, Wrap};
+
+/// Turns a panic or an error escaping the handler into a 500 JSON response,
+/// instead of a failed invocation with no body for the client. Put it
+/// inside the request id, timing and CORS layers so the 500 still carries
+/// their headers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CatchPanicLayer;
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = Middleware<Self, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Middleware::new(*self, inner)
+    }
+}
+
+impl Wrap for CatchPanicLayer {
+    fn wrap<S: LambdaService>(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+        mut inner: S,
+    ) -> HandlerFuture {
+        let response = match catch_unwind(AssertUnwindSafe(|| inner.call(event))) {
+            Ok(response) => response,
+            Err(panic) => return Box::pin(async move { panic_response(panic) }),
+        };
+
+        Box::pin(async move {
+            match AssertUnwindSafe(response).catch_unwind().await {
+                Ok(Ok(response)) => Ok(response),
+                Ok(Err(error)) => AppError::from_boxed(error).into_response(),
+                Err(panic) => panic_response(panic),
+            }
+        })
+    }
+}
+
+fn panic_response(panic: Box<dyn Any + Send>) -> Result<ApiGatewayProxyResponse, Error> {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_owned());
+
+    AppError::Internal(format!("handler panicked: {}", message)).into_response()
+}
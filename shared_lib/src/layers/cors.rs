@@ -0,0 +1,61 @@
+use lambda_runtime::LambdaEvent;
+use tower::Layer;
+
+use crate::{
+    router::HandlerFuture,
+    utils::cors::{cors, cors_headers},
+    RequestPayload,
+};
+
+use super::{LambdaService, Middleware, Wrap};
+
+/// Answers every `OPTIONS` preflight and adds the CORS headers to responses
+/// that do not set them, such as redirects.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CorsLayer;
+
+impl<S> Layer<S> for CorsLayer {
+    type Service = Middleware<Self, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Middleware::new(*self, inner)
+    }
+}
+
+impl Wrap for CorsLayer {
+    fn wrap<S: LambdaService>(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+        mut inner: S,
+    ) -> HandlerFuture {
+        let is_preflight = event
+            .payload
+            .http_method
+            .as_deref()
+            .is_some_and(|method| method.eq_ignore_ascii_case("OPTIONS"));
+
+        if is_preflight {
+            return Box::pin(async { cors() });
+        }
+
+        let response = inner.call(event);
+
+        Box::pin(async move {
+            let mut response = response.await?;
+
+            for (name, value) in cors_headers() {
+                let Some(name) = name else { continue };
+
+                if !response.headers.contains_key(&name) {
+                    response.headers.insert(name.clone(), value.clone());
+                }
+
+                if !response.multi_value_headers.contains_key(&name) {
+                    response.multi_value_headers.insert(name, value);
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,145 @@
+pub mod body_limit;
+pub mod catch_panic;
+pub mod cors;
+pub mod request_id;
+pub mod session_cookie;
+pub mod timing;
+
+use std::task::{Context, Poll};
+
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_runtime::{Error, LambdaEvent};
+use tower::Service;
+
+use crate::{router::HandlerFuture, RequestPayload};
+
+pub use self::{
+    body_limit::BodyLimitLayer,
+    catch_panic::CatchPanicLayer,
+    cors::CorsLayer,
+    request_id::{RequestIdHeader, RequestIdLayer},
+    session_cookie::SessionCookieRefreshLayer,
+    timing::TimingLayer,
+};
+
+/// A function's service: an API Gateway event in, a proxy response out.
+/// Everything from `service_fn(handler)` up to a full layer stack is one.
+pub trait LambdaService:
+    Service<
+        LambdaEvent<RequestPayload>,
+        Response = ApiGatewayProxyResponse,
+        Error = Error,
+        Future: Send,
+    > + Clone
+    + Send
+    + 'static
+{
+}
+
+impl<S> LambdaService for S where
+    S: Service<
+            LambdaEvent<RequestPayload>,
+            Response = ApiGatewayProxyResponse,
+            Error = Error,
+            Future: Send,
+        > + Clone
+        + Send
+        + 'static
+{
+}
+
+/// The part each layer implements: what to do with one event, given the
+/// service it wraps. [`Middleware`] handles the `tower` plumbing around it.
+pub trait Wrap: Clone + Send + 'static {
+    fn wrap<S: LambdaService>(&self, event: LambdaEvent<RequestPayload>, inner: S)
+        -> HandlerFuture;
+}
+
+/// The service a layer in this module produces around `S`.
+#[derive(Clone)]
+pub struct Middleware<W, S> {
+    wrap: W,
+    inner: S,
+}
+
+impl<W, S> Middleware<W, S> {
+    pub fn new(wrap: W, inner: S) -> Self {
+        Self { wrap, inner }
+    }
+}
+
+impl<W: Wrap, S: LambdaService> Service<LambdaEvent<RequestPayload>> for Middleware<W, S> {
+    type Response = ApiGatewayProxyResponse;
+    type Error = Error;
+    type Future = HandlerFuture;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, event: LambdaEvent<RequestPayload>) -> Self::Future {
+        // Hand the service that was polled ready to the call and keep a fresh clone.
+        let inner = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, inner);
+
+        self.wrap.wrap(event, inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::http::StatusCode;
+    use lambda_runtime::Context;
+    use tower::{service_fn, Layer, ServiceBuilder, ServiceExt};
+
+    use super::*;
+    use crate::AppSuccessResponse;
+
+    fn event(payload: RequestPayload) -> LambdaEvent<RequestPayload> {
+        let mut context = Context::default();
+        context.request_id = "lambda-request-id".to_owned();
+
+        LambdaEvent::new(payload, context)
+    }
+
+    #[tokio::test]
+    async fn panics_become_500_responses_with_a_request_id() {
+        let response = ServiceBuilder::new()
+            .layer(RequestIdLayer)
+            .layer(CatchPanicLayer)
+            .service_fn(|_: LambdaEvent<RequestPayload>| async {
+                if true {
+                    panic!("boom");
+                }
+
+                AppSuccessResponse::new(StatusCode::OK, None, None)
+            })
+            .oneshot(event(RequestPayload::default()))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_code, 500);
+        assert_eq!(
+            response.headers.get("x-request-id").unwrap(),
+            "lambda-request-id"
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_never_reach_the_handler() {
+        let handler = service_fn(|_: LambdaEvent<RequestPayload>| async {
+            AppSuccessResponse::new(StatusCode::OK, None, None)
+        });
+
+        let response = BodyLimitLayer::new(4)
+            .layer(handler)
+            .oneshot(event(RequestPayload {
+                body: Some("12345".to_owned()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status_code, 413);
+    }
+}
@@ -0,0 +1,77 @@
+use aws_lambda_events::http::{HeaderName, HeaderValue};
+use lambda_runtime::LambdaEvent;
+use serde_json::Value;
+use tower::Layer;
+
+use crate::{
+    router::{extract, HandlerFuture},
+    utils::headers::get_header,
+    RequestPayload,
+};
+
+use super::{LambdaService, Middleware, Wrap};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Extracts the request id [`RequestIdLayer`] settled on.
+pub struct RequestIdHeader;
+
+impl extract::HeaderName for RequestIdHeader {
+    const NAME: &'static str = REQUEST_ID_HEADER;
+}
+
+/// Gives every request an id. A well-formed `X-Request-Id` from the client
+/// is kept, otherwise the Lambda invocation id is used. The id is written
+/// back into the request headers for handlers and echoed on the response.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = Middleware<Self, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Middleware::new(*self, inner)
+    }
+}
+
+impl Wrap for RequestIdLayer {
+    fn wrap<S: LambdaService>(
+        &self,
+        mut event: LambdaEvent<RequestPayload>,
+        mut inner: S,
+    ) -> HandlerFuture {
+        let request_id = get_header(&event, REQUEST_ID_HEADER)
+            .filter(|request_id| is_valid_request_id(request_id))
+            .unwrap_or_else(|| event.context.request_id.clone());
+
+        let headers = event.payload.headers.get_or_insert_with(Default::default);
+        headers.retain(|name, _| !name.eq_ignore_ascii_case(REQUEST_ID_HEADER));
+        headers.insert(
+            REQUEST_ID_HEADER.to_owned(),
+            Value::from(request_id.clone()),
+        );
+
+        let response = inner.call(event);
+
+        Box::pin(async move {
+            let mut response = response.await?;
+
+            if let Ok(request_id) = HeaderValue::from_str(&request_id) {
+                let name = HeaderName::from_static(REQUEST_ID_HEADER);
+
+                response.headers.insert(name.clone(), request_id.clone());
+                response.multi_value_headers.insert(name, request_id);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+fn is_valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= 128
+        && request_id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-_.:".contains(&byte))
+}
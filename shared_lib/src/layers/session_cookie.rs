@@ -0,0 +1,71 @@
+use std::env;
+
+use lambda_runtime::LambdaEvent;
+use mongodb::bson::oid::ObjectId;
+use tower::Layer;
+
+use crate::{
+    auth::{
+        csrf::{csrf_cookie, csrf_cookie_name},
+        impersonation::{impersonation_cookie, impersonation_cookie_name},
+    },
+    router::HandlerFuture,
+    utils::cookie::{append_reissued_cookies, reissue_cookie, session_cookie},
+    RequestPayload,
+};
+
+use super::{LambdaService, Middleware, Wrap};
+
+/// Session cookie upkeep for cookie authenticated functions. The session,
+/// CSRF and impersonation cookies sealed with a rotated out key are re-sealed
+/// under the primary key on the way out, unless the handler sets them itself.
+///
+/// It does not authenticate anything. Each function resolves credentials
+/// and checks route permissions after routing, since those depend on the
+/// matched route.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionCookieRefreshLayer;
+
+impl<S> Layer<S> for SessionCookieRefreshLayer {
+    type Service = Middleware<Self, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Middleware::new(*self, inner)
+    }
+}
+
+impl Wrap for SessionCookieRefreshLayer {
+    fn wrap<S: LambdaService>(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+        mut inner: S,
+    ) -> HandlerFuture {
+        let reissued_cookies = [
+            reissue_cookie(
+                &event,
+                &env::var("COOKIE_NAME").unwrap_or_default(),
+                |username| Some(session_cookie(username)),
+            ),
+            reissue_cookie(&event, &csrf_cookie_name(), |csrf_token| {
+                Some(csrf_cookie(csrf_token))
+            }),
+            reissue_cookie(&event, &impersonation_cookie_name(), |session_id| {
+                ObjectId::parse_str(session_id)
+                    .ok()
+                    .map(|session_id| impersonation_cookie(&session_id))
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        let response = inner.call(event);
+
+        Box::pin(async move {
+            let mut response = response.await?;
+            append_reissued_cookies(&mut response, reissued_cookies);
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Instant;
+
+use aws_lambda_events::http::{HeaderName, HeaderValue};
+use lambda_runtime::LambdaEvent;
+use tower::Layer;
+
+use crate::{
+    router::{request_method, HandlerFuture},
+    RequestPayload,
+};
+
+use super::{LambdaService, Middleware, Wrap};
+
+/// Logs method, path, status and duration of every request, and reports the
+/// duration to the browser in a `Server-Timing` header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimingLayer;
+
+impl<S> Layer<S> for TimingLayer {
+    type Service = Middleware<Self, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Middleware::new(*self, inner)
+    }
+}
+
+impl Wrap for TimingLayer {
+    fn wrap<S: LambdaService>(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+        mut inner: S,
+    ) -> HandlerFuture {
+        let started_at = Instant::now();
+        let method = request_method(&event);
+        let path = event.payload.path.clone().unwrap_or_default();
+
+        let response = inner.call(event);
+
+        Box::pin(async move {
+            let mut response = response.await?;
+            let duration = started_at.elapsed().as_secs_f64() * 1000.0;
+
+            println!(
+                "{} {} {} {:.1}ms",
+                method, path, response.status_code, duration
+            );
+
+            if let Ok(server_timing) = HeaderValue::from_str(&format!("app;dur={:.1}", duration)) {
+                let name = HeaderName::from_static("server-timing");
+
+                response.headers.insert(name.clone(), server_timing.clone());
+                response.multi_value_headers.insert(name, server_timing);
+            }
+
+            Ok(response)
+        })
+    }
+}
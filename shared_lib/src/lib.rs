@@ -1,17 +1,34 @@
+// Lets `#[derive(Model)]` name this crate as `::shared_lib` inside it too.
+extern crate self as shared_lib;
+
+pub mod auth;
 pub mod database;
+pub mod error;
+pub mod layers;
+pub mod mailer;
 pub mod models;
+pub mod router;
 pub mod traits;
 pub mod utils;
 
-use std::{clone, collections::HashMap, env, ops::Deref};
+/// Dependencies the code generated by `#[derive(Model)]` refers to, so a
+/// crate deriving it does not need them itself.
+#[doc(hidden)]
+pub mod __private {
+    pub use chrono;
+    pub use inventory;
+    pub use mongodb;
+    pub use serde;
+}
+
+use std::{collections::HashMap, env};
 
 use aws_lambda_events::{
     apigw::ApiGatewayProxyResponse,
     encodings::Body,
     http::{HeaderMap, StatusCode},
 };
-use lambda_runtime::{tower::util::error, Error};
-use mongodb::bson::Document;
+use lambda_runtime::Error;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -46,8 +63,8 @@ pub struct PaginationMetadata {
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
-pub struct PaginatedData {
-    pub documents: Vec<Document>,
+pub struct PaginatedData<T> {
+    pub documents: Vec<T>,
     pub metadata: PaginationMetadata,
 }
 
@@ -64,12 +81,16 @@ pub struct ResponseBody {
     status: ResponseStatus,
     message: Option<String>,
     data: Option<Value>,
+    /// Present only on responses produced while impersonating another user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    impersonation: Option<Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AppSuccessResponse {}
 
 impl AppSuccessResponse {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(
         status_code: StatusCode,
         message: Option<String>,
@@ -91,6 +112,7 @@ impl AppSuccessResponse {
             status: ResponseStatus::Success,
             message,
             data,
+            impersonation: None,
         };
 
         let response_body_json = serde_json::to_string(&response_body).unwrap_or_default();
@@ -109,6 +131,7 @@ impl AppSuccessResponse {
 pub struct AppErrorResponse {}
 
 impl AppErrorResponse {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(
         status_code: StatusCode,
         message: Option<String>,
@@ -130,6 +153,7 @@ impl AppErrorResponse {
             status: ResponseStatus::Error,
             message,
             data,
+            impersonation: None,
         };
 
         let response_body_json = serde_json::to_string(&response_body).unwrap_or_default();
@@ -147,7 +171,7 @@ impl AppErrorResponse {
 #[derive(Debug)]
 pub enum DataInsertError {
     FieldValidationError(validator::ValidationErrors),
-    MongoDuplicateError(mongodb::error::WriteError),
+    MongoDuplicateError(error::DuplicateKey),
     MongoWriteError(mongodb::error::WriteError),
     OtherMongoError(mongodb::error::Error),
 }
@@ -163,7 +187,7 @@ impl From<mongodb::error::Error> for DataInsertError {
         match error.kind.as_ref() {
             mongodb::error::ErrorKind::Write(mongodb::error::WriteFailure::WriteError(error)) => {
                 if error.code == 11000 {
-                    Self::MongoDuplicateError(error.clone())
+                    Self::MongoDuplicateError(error::DuplicateKey::from_write_error(error))
                 } else {
                     Self::MongoWriteError(error.clone())
                 }
@@ -0,0 +1,47 @@
+use std::{env, fs, path::PathBuf};
+
+use chrono::Utc;
+
+use super::{EmailMessage, MailTransport, MailerError};
+
+#[derive(Debug, Clone)]
+pub struct FileTransport {
+    pub directory: PathBuf,
+}
+
+impl FileTransport {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    pub fn from_env() -> Self {
+        let directory = env::var("MAILER_FILE_DIR").unwrap_or("/tmp/mail".to_owned());
+
+        Self::new(PathBuf::from(directory))
+    }
+}
+
+impl MailTransport for FileTransport {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailerError> {
+        fs::create_dir_all(&self.directory)?;
+
+        let file_name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%d%H%M%S%f"),
+            message.to.replace(['@', '/', '\\'], "_")
+        );
+
+        let contents = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\r\n{}\r\n",
+            message.from,
+            message.to,
+            message.subject,
+            Utc::now().to_rfc2822(),
+            message.text
+        );
+
+        fs::write(self.directory.join(file_name), contents)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use std::env;
+
+use super::{EmailMessage, MailTransport, MailerError};
+
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    pub url: String,
+    pub api_key: String,
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new(url: String, api_key: String) -> Self {
+        Self {
+            url,
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let url = env::var("MAILER_HTTP_URL").unwrap_or_default();
+        let api_key = env::var("MAILER_HTTP_API_KEY").unwrap_or_default();
+
+        Self::new(url, api_key)
+    }
+}
+
+impl MailTransport for HttpTransport {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailerError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .bearer_auth(&self.api_key)
+            .json::<EmailMessage>(message)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(MailerError::DeliveryRejected(response.status().as_u16()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+pub mod file_transport;
+pub mod http_transport;
+
+use std::env;
+
+use serde::{Deserialize, Serialize};
+
+use self::{file_transport::FileTransport, http_transport::HttpTransport};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EmailMessage {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+}
+
+impl EmailMessage {
+    pub fn new(to: String, subject: String, text: String) -> Self {
+        Self {
+            from: env::var("MAILER_FROM").unwrap_or_default(),
+            to,
+            subject,
+            text,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MailerError {
+    IoError(std::io::Error),
+    HttpError(reqwest::Error),
+    DeliveryRejected(u16),
+}
+
+impl From<std::io::Error> for MailerError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IoError(error)
+    }
+}
+
+impl From<reqwest::Error> for MailerError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::HttpError(error)
+    }
+}
+
+impl std::fmt::Display for MailerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IoError(error) => write!(f, "Could not write email to file: {}", error),
+            Self::HttpError(error) => write!(f, "Could not send email: {}", error),
+            Self::DeliveryRejected(status) => {
+                write!(f, "Email provider rejected message with status {}", status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MailerError {}
+
+pub trait MailTransport {
+    fn send(
+        &self,
+        message: &EmailMessage,
+    ) -> impl std::future::Future<Output = Result<(), MailerError>> + Send;
+}
+
+/// Mail transport selected through `MAILER_TRANSPORT`.
+///
+/// `file` writes every message to `MAILER_FILE_DIR` and is meant for local
+/// development and testing. `http` posts messages to `MAILER_HTTP_URL`.
+#[derive(Debug, Clone)]
+pub enum Mailer {
+    File(FileTransport),
+    Http(HttpTransport),
+}
+
+impl Mailer {
+    pub fn from_env() -> Self {
+        let transport = env::var("MAILER_TRANSPORT").unwrap_or_default();
+
+        match transport.to_lowercase().as_str() {
+            "http" => Self::Http(HttpTransport::from_env()),
+            _ => Self::File(FileTransport::from_env()),
+        }
+    }
+}
+
+impl MailTransport for Mailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailerError> {
+        match self {
+            Self::File(transport) => transport.send(message).await,
+            Self::Http(transport) => transport.send(message).await,
+        }
+    }
+}
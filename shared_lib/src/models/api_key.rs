@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{auth::permission::Permission, traits::model_traits::Model};
+
+/// Prefix shared by every API key so they are easy to recognise in logs and secret scanners.
+pub const API_KEY_PREFIX: &str = "ldk";
+
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Model)]
+#[model(timestamps, index(user_id = 1))]
+pub struct ApiKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[validate(required(message = "Key owner is required"))]
+    pub user_id: Option<ObjectId>,
+    #[validate(
+        required(message = "Name is required"),
+        length(min = 1, message = "Name is required"),
+        length(max = 50, message = "Name cannot be more than 50 characters")
+    )]
+    pub name: Option<String>,
+    #[validate(required)]
+    #[model(unique)]
+    pub prefix: Option<String>,
+    #[validate(required)]
+    pub secret_hash: Option<String>,
+    #[validate(required(message = "At least one scope is required"))]
+    pub scopes: Option<Vec<Permission>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A key as listed to its owner, read without `secret_hash`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeySummary {
+    #[serde(rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub user_id: Option<ObjectId>,
+    pub name: Option<String>,
+    pub prefix: Option<String>,
+    pub scopes: Option<Vec<Permission>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn is_usable(&self) -> bool {
+        let is_expired = self
+            .expires_at
+            .is_some_and(|expires_at| expires_at < Utc::now());
+
+        self.revoked_at.is_none() && !is_expired
+    }
+
+    /// Splits a presented key of the form `ldk_<prefix>_<secret>` into prefix and secret.
+    pub fn parse_key(key: &str) -> Option<(&str, &str)> {
+        let key = key.strip_prefix(API_KEY_PREFIX)?.strip_prefix('_')?;
+
+        key.split_once('_')
+    }
+}
+
+impl Default for ApiKey {
+    fn default() -> Self {
+        Self {
+            id: None,
+            user_id: None,
+            name: None,
+            prefix: None,
+            secret_hash: None,
+            scopes: None,
+            expires_at: None,
+            last_used_at: None,
+            revoked_at: None,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        }
+    }
+}
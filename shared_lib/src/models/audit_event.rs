@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Database,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    auth::context::AuthContext,
+    database::queries,
+    traits::model_traits::{Model, ModelTraits},
+    utils::headers::RequestMetadata,
+};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum AuditAction {
+    #[serde(rename = "login")]
+    Login,
+    #[serde(rename = "login_failed")]
+    LoginFailed,
+    #[serde(rename = "post_created")]
+    PostCreated,
+    #[serde(rename = "post_updated")]
+    PostUpdated,
+    #[serde(rename = "post_published")]
+    PostPublished,
+    #[serde(rename = "post_unpublished")]
+    PostUnpublished,
+    #[serde(rename = "role_changed")]
+    RoleChanged,
+    #[serde(rename = "user_deactivated")]
+    UserDeactivated,
+    #[serde(rename = "user_reactivated")]
+    UserReactivated,
+    #[serde(rename = "user_deleted")]
+    UserDeleted,
+    #[serde(rename = "password_reset_forced")]
+    PasswordResetForced,
+    #[serde(rename = "invitation_created")]
+    InvitationCreated,
+    #[serde(rename = "api_key_created")]
+    ApiKeyCreated,
+    #[serde(rename = "api_key_revoked")]
+    ApiKeyRevoked,
+    #[serde(rename = "impersonation_started")]
+    ImpersonationStarted,
+    #[serde(rename = "impersonation_ended")]
+    ImpersonationEnded,
+    #[serde(rename = "account_erased")]
+    AccountErased,
+}
+
+/// An entry in the append-only `audit_events` collection.
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Model)]
+#[model(collection = "audit_events", append_only)]
+#[model(index(actor_id = 1, created_at = -1), index(target_type = 1, target_id = 1))]
+pub struct AuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// Missing for failed logins, where nobody could be identified.
+    pub actor_id: Option<ObjectId>,
+    /// The super admin behind `actor_id` when the action happened during impersonation.
+    pub impersonator_id: Option<ObjectId>,
+    #[validate(required(message = "Action is required"))]
+    pub action: Option<AuditAction>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub before: Option<Document>,
+    pub after: Option<Document>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    #[validate(required)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl AuditEvent {
+    /// An event performed by the caller, crediting the impersonator if there is one.
+    pub fn by(auth_context: &AuthContext) -> Self {
+        Self {
+            actor_id: Some(auth_context.user_id),
+            impersonator_id: auth_context
+                .impersonation
+                .as_ref()
+                .map(|impersonation| impersonation.impersonator_id),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_request_metadata(self, request_metadata: &RequestMetadata) -> Self {
+        Self {
+            ip_address: request_metadata.ip_address.clone(),
+            user_agent: request_metadata.user_agent.clone(),
+            ..self
+        }
+    }
+
+    /// Clears the IP address and user agent from every event by or about
+    /// `user_id`, for account erasure. This is the one write the append-only
+    /// log allows, so it goes to the queries directly rather than through
+    /// `update_many`, and it touches nothing else.
+    pub async fn scrub_request_metadata(
+        database: &Database,
+        user_id: ObjectId,
+    ) -> mongodb::error::Result<u64> {
+        let update_result = queries::update_many::<Self>(
+            database,
+            doc! {"$or": [
+                {"actor_id": user_id},
+                {"impersonator_id": user_id},
+                {"target_type": "user", "target_id": user_id.to_hex()},
+            ]},
+            doc! {"$unset": {"ip_address": "", "user_agent": ""}},
+        )
+        .await?;
+
+        Ok(update_result.modified_count)
+    }
+
+    /// Saves the event. Auditing never fails the action being audited, so
+    /// errors are only logged.
+    pub async fn record(self, database: &Database) {
+        if let Err(error) = self.save(database).await {
+            eprintln!(
+                "Failed to record audit event {:?}: {:?}",
+                self.action, error
+            );
+        }
+    }
+}
+
+impl Default for AuditEvent {
+    fn default() -> Self {
+        Self {
+            id: None,
+            actor_id: None,
+            impersonator_id: None,
+            action: None,
+            target_type: None,
+            target_id: None,
+            before: None,
+            after: None,
+            ip_address: None,
+            user_agent: None,
+            created_at: Some(Utc::now()),
+        }
+    }
+}
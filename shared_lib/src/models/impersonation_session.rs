@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::traits::model_traits::Model;
+
+/// A super admin acting as another user. The session id travels in an
+/// encrypted cookie next to the super admin's own session cookie.
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Model)]
+#[model(timestamps, index(impersonator_id = 1, created_at = -1))]
+pub struct ImpersonationSession {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[validate(required)]
+    pub impersonator_id: Option<ObjectId>,
+    #[validate(required(message = "User to impersonate is required"))]
+    pub user_id: Option<ObjectId>,
+    pub allow_destructive: Option<bool>,
+    #[validate(length(max = 500, message = "Reason cannot be more than 500 characters"))]
+    pub reason: Option<String>,
+    #[validate(required)]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ImpersonationSession {
+    pub fn is_active(&self) -> bool {
+        let is_expired = self
+            .expires_at
+            .is_none_or(|expires_at| expires_at < Utc::now());
+
+        self.ended_at.is_none() && !is_expired
+    }
+}
+
+impl Default for ImpersonationSession {
+    fn default() -> Self {
+        Self {
+            id: None,
+            impersonator_id: None,
+            user_id: None,
+            allow_destructive: Some(false),
+            reason: None,
+            expires_at: None,
+            ended_at: None,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{models::user::UserRole, traits::model_traits::Model};
+
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Model)]
+#[model(timestamps)]
+pub struct Invitation {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[validate(required, email(message = "Enter a valid email address."))]
+    pub email: Option<String>,
+    #[validate(required(message = "Role is required"))]
+    pub role: Option<UserRole>,
+    #[validate(required)]
+    #[model(unique)]
+    pub token_hash: Option<String>,
+    #[validate(required(message = "Inviter is required"))]
+    pub invited_by: Option<ObjectId>,
+    #[validate(required)]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub redeemed_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// An invitation as listed to admins, read without `token_hash`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingInvitation {
+    #[serde(rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub email: Option<String>,
+    pub role: Option<UserRole>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Invitation {
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at < Utc::now(),
+            None => true,
+        }
+    }
+}
+
+impl Default for Invitation {
+    fn default() -> Self {
+        Self {
+            id: None,
+            email: None,
+            role: Some(UserRole::User),
+            token_hash: None,
+            invited_by: None,
+            expires_at: None,
+            redeemed_at: None,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        }
+    }
+}
@@ -1,2 +1,7 @@
+pub mod api_key;
+pub mod audit_event;
+pub mod impersonation_session;
+pub mod invitation;
+pub mod post;
+pub mod refresh_token;
 pub mod user;
-pub mod post;
\ No newline at end of file
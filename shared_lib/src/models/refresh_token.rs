@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::traits::model_traits::Model;
+
+/// A single-use refresh token. Every rotation creates a new token in the same
+/// family, so presenting an already used token reveals that it was stolen.
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Model)]
+#[model(timestamps, index(user_id = 1, revoked_at = 1))]
+pub struct RefreshToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[validate(required)]
+    pub user_id: Option<ObjectId>,
+    #[validate(required)]
+    pub family_id: Option<String>,
+    #[validate(required)]
+    #[model(unique)]
+    pub token_hash: Option<String>,
+    #[validate(required)]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// When MongoDB deletes the token. A BSON date, unlike the other dates,
+    /// because TTL indexes only expire on those.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[model(ttl = 0)]
+    pub purge_at: Option<mongodb::bson::DateTime>,
+    #[validate(required)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[validate(required)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at < Utc::now(),
+            None => true,
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        Self {
+            id: None,
+            user_id: None,
+            family_id: None,
+            token_hash: None,
+            expires_at: None,
+            used_at: None,
+            revoked_at: None,
+            purge_at: None,
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        }
+    }
+}
@@ -1,19 +1,19 @@
-use std::{default, error::Error};
-
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{DateTime, Utc};
-use inflector::Inflector;
-use mongodb::{
-    bson::{doc, document, to_document, Document},
-    options::{FindOptions, IndexOptions},
-    Cursor, Database, IndexModel,
-};
+use mongodb::bson::{doc, oid::ObjectId, Document};
 use serde::{Deserialize, Serialize};
-use validator::{HasLen, Validate, ValidationError, ValidationErrors};
+use validator::Validate;
+
+use crate::traits::model_traits::Model;
 
-use crate::{
-    traits::model_traits::ModelTraits, DataInsertError, PaginatedData, PaginationMetadata,
-};
-use futures_util::stream::StreamExt;
+/// Username of the account that keeps the posts of erased users.
+pub const DELETED_USER_USERNAME: &str = "deleted-user";
+
+/// The unique username an erased account is renamed to. The id is base64
+/// encoded so the name stays within the 25 character limit.
+pub fn erased_username(user_id: &ObjectId) -> String {
+    format!("deleted-{}", URL_SAFE_NO_PAD.encode(user_id.bytes()))
+}
 
 #[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
 pub enum UserRole {
@@ -26,217 +26,133 @@ pub enum UserRole {
     User,
 }
 
+impl UserRole {
+    /// Whether a user holding this role may hand out `role` to someone else.
+    pub fn can_assign_role(&self, role: &UserRole) -> bool {
+        match self {
+            UserRole::SuperAdmin => true,
+            UserRole::Admin => matches!(role, UserRole::Admin | UserRole::User),
+            UserRole::User => false,
+        }
+    }
+}
+
 // impl Default for UserRole {
 //     fn default() -> Self {
 //         UserRole::Admin
 //     }
 // }
 
-#[derive(Debug, Serialize, Deserialize, Validate, Clone)]
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Model)]
+#[model(timestamps)]
 pub struct User {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
     #[validate(
         required,
         length(min = 3, message = "Username cannot be less than 3 characters"),
         length(max = 25, message = "Username cannot be more than 25 characters")
     )]
+    #[model(unique)]
     pub username: Option<String>,
     #[validate(required, email(message = "Enter a valid email address."))]
+    #[model(unique)]
     pub email: Option<String>,
     #[validate(required(message = "Password is required"))]
     pub password: Option<String>,
     #[validate(required(message = "Role is required"))]
     pub role: Option<UserRole>,
     pub profile_image: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub is_active: Option<bool>,
+    pub password_reset_required: Option<bool>,
+    pub password_reset_token_hash: Option<String>,
+    pub password_reset_expires_at: Option<DateTime<Utc>>,
+    pub erased_at: Option<DateTime<Utc>>,
+    pub purge_after: Option<DateTime<Utc>>,
     #[validate(required)]
     pub created_at: Option<DateTime<Utc>>,
     #[validate(required)]
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
-pub struct UniqueUserFields {
-    email: bool,
-    username: bool,
-}
-
-impl ModelTraits for User {
-    fn get_struct_name_as_plural_string() -> String {
-        stringify!(User).to_lowercase().to_plural()
+impl User {
+    /// Users created before deactivation existed have no `is_active` field.
+    pub fn is_active(&self) -> bool {
+        self.is_active.unwrap_or(true)
     }
 
-    async fn set_unique_fields(database: &Database) -> Result<(), DataInsertError> {
-        let collection_name = Self::get_struct_name_as_plural_string();
-        let unique_fields = UniqueUserFields {
-            email: true,
-            username: true,
-        };
-        let bson_doc = to_document(&unique_fields).unwrap();
-
-        for (key, _) in bson_doc.iter() {
-            let options = IndexOptions::builder().unique(true).build();
-            let model = IndexModel::builder()
-                .keys(doc! {key: 1})
-                .options(Some(options))
-                .build();
-
-            database
-                .collection::<Self>(&collection_name)
-                .create_index(model, None)
-                .await?;
-        }
-
-        Ok(())
+    /// Erased accounts are hard deleted once their grace period has passed.
+    pub fn is_due_for_purge(&self) -> bool {
+        self.erased_at.is_some()
+            && self
+                .purge_after
+                .is_some_and(|purge_after| purge_after <= Utc::now())
     }
 
-    async fn save(
-        &self,
-        database: &Database,
-    ) -> Result<mongodb::results::InsertOneResult, DataInsertError> {
-        self.validate()?;
-        Self::set_unique_fields(database).await?;
-
-        let collection_name = Self::get_struct_name_as_plural_string();
-
-        let database_insert_response = database
-            .collection::<Self>(&collection_name)
-            .insert_one(self, None)
-            .await?;
-
-        Ok(database_insert_response)
-    }
-
-    async fn find(
-        database: &Database,
-        filter: document::Document,
-        projection: Option<document::Document>,
-        sort: Option<document::Document>,
-        limit: i64,
-    ) -> mongodb::error::Result<Vec<Document>> {
-        let collection_name = Self::get_struct_name_as_plural_string();
-
-        let find_options = FindOptions::builder()
-            .projection(projection)
-            .sort(sort)
-            .limit(Some(limit))
-            .build();
-
-        let mut database_find_cursor = database
-            .collection(&collection_name)
-            .find(filter, find_options)
-            .await?;
-
-        let mut documents = Vec::new();
-
-        while let Some(result) = database_find_cursor.next().await {
-            match result {
-                Ok(document) => documents.push(document),
-                Err(_) => (),
-            }
+    /// Projection that leaves out credentials, for responses sent back to clients.
+    pub fn safe_projection() -> Document {
+        doc! {
+            "password": false,
+            "password_reset_token_hash": false,
         }
-
-        Ok(documents)
     }
+}
 
-    async fn find_paginated(
-        database: &Database,
-        filter: document::Document,
-        projection: Option<document::Document>,
-        sort: Option<document::Document>,
-        current_page: Option<i64>,
-        items_per_page: Option<i64>,
-    ) -> mongodb::error::Result<PaginatedData> {
-        let collection_name = Self::get_struct_name_as_plural_string();
-
-        let current_page = if let Some(page_no) = current_page {
-            if page_no < 1 {
-                1
-            } else {
-                page_no
-            }
-        } else {
-            1
-        };
-
-        let items_per_page = if let Some(items_per_page_no) = items_per_page {
-            if items_per_page_no < 1 {
-                1
-            } else {
-                items_per_page_no
-            }
-        } else {
-            10
-        };
-
-        let total_items = database
-            .collection::<Self>(&collection_name)
-            .count_documents(filter.clone(), None)
-            .await?;
-
-        let total_pages = (total_items as f64 / items_per_page as f64).ceil() as u64;
-
-        let find_options = FindOptions::builder()
-            .projection(projection)
-            .sort(sort)
-            .limit(Some(items_per_page))
-            .skip(Some((current_page as u64 - 1) * items_per_page as u64))
-            .build();
-
-        let mut database_find_cursor = database
-            .collection(&collection_name)
-            .find(filter, find_options)
-            .await?;
-
-        let mut paginated_users_data = PaginatedData {
-            documents: Vec::new(),
-            metadata: PaginationMetadata {
-                ..Default::default()
-            },
-        };
-
-        while let Some(result) = database_find_cursor.next().await {
-            match result {
-                Ok(document) => paginated_users_data.documents.push(document),
-                Err(_) => (),
-            }
-        }
-
-        if paginated_users_data.documents.length() < 1 {
-            return Ok(paginated_users_data);
-        }
-        paginated_users_data.metadata = PaginationMetadata {
-            current_page: Some(current_page as u64),
-            total_pages: Some(total_pages),
-            total_items: Some(total_items),
-            items_per_page: Some(items_per_page as u64),
-        };
-        Ok(paginated_users_data)
-    }
-
-    async fn count_documents(
-        database: &Database,
-        filter: document::Document,
-    ) -> mongodb::error::Result<u64> {
-        let collection_name = Self::get_struct_name_as_plural_string();
-
-        let total_items = database
-            .collection::<Self>(&collection_name)
-            .count_documents(filter.clone(), None)
-            .await?;
-
-        Ok(total_items)
-    }
+/// A user as read with [`User::safe_projection`], for sending to clients.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserProfile {
+    #[serde(rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub role: Option<UserRole>,
+    pub profile_image: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub is_active: Option<bool>,
+    pub password_reset_required: Option<bool>,
+    pub password_reset_expires_at: Option<DateTime<Utc>>,
+    pub erased_at: Option<DateTime<Utc>>,
+    pub purge_after: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 impl Default for User {
     fn default() -> Self {
         Self {
+            id: None,
             username: None,
             email: None,
             password: None,
             role: Some(UserRole::User),
             profile_image: None,
+            email_verified_at: None,
+            is_active: Some(true),
+            password_reset_required: Some(false),
+            password_reset_token_hash: None,
+            password_reset_expires_at: None,
+            erased_at: None,
+            purge_after: None,
             created_at: Some(Utc::now()),
             updated_at: Some(Utc::now()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn erased_usernames_pass_validation() {
+        let user = User {
+            username: Some(erased_username(&ObjectId::new())),
+            email: Some("user@deleted.invalid".to_owned()),
+            password: Some("hash".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(user.validate().is_ok(), "{:?}", user.username);
+    }
+}
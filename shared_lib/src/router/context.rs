@@ -0,0 +1,144 @@
+use std::future::Future;
+
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::Method};
+use lambda_runtime::{Error, LambdaEvent};
+use mongodb::Database;
+
+use crate::{
+    auth::{
+        context::AuthContext,
+        csrf::is_valid_csrf_request,
+        guard::{authorize, AUTHENTICATION_REQUIRED},
+        impersonation::flag_impersonated_response,
+    },
+    database::client::connect_db,
+    error::AppError,
+    utils::headers::RequestMetadata,
+    RequestPayload,
+};
+
+use super::{request::RouteRequest, request_method, Access, HandlerFuture, Router};
+
+/// What a function resolves for a request before calling its route handler.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub database: Database,
+    pub request_metadata: RequestMetadata,
+    /// The caller, for routes that sit behind authentication.
+    pub auth_context: Option<AuthContext>,
+}
+
+impl Router<RequestContext> {
+    /// Routes a request for a function with signed-in routes: checks the
+    /// CSRF token, resolves the caller the route's access asks for, runs the
+    /// handler and flags the response when the caller is impersonating.
+    pub async fn dispatch(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+    ) -> Result<ApiGatewayProxyResponse, Error> {
+        let path = event.payload.path.clone().unwrap_or_default();
+
+        let route = match self.find(&request_method(&event), &path) {
+            Ok(route) => route,
+            Err(route_miss) => return route_miss.into_response(),
+        };
+
+        if !is_valid_csrf_request(&event) {
+            return AppError::Forbidden("Invalid or missing CSRF token".to_owned()).into_response();
+        }
+
+        let database = connect_db().await?;
+
+        let auth_context = match authorize(&event, &database, route.access()).await {
+            Ok(auth_context) => auth_context,
+            Err(response) => return Ok(response),
+        };
+
+        let context = RequestContext {
+            database,
+            request_metadata: RequestMetadata::from_event(&event),
+            auth_context: auth_context.clone(),
+        };
+
+        let mut response = route.call(event, context).await?;
+
+        if let Some(auth_context) = auth_context.as_ref() {
+            flag_impersonated_response(&mut response, auth_context);
+        }
+
+        Ok(response)
+    }
+
+    /// Declares a route that only callers with `access` reach. The handler
+    /// gets the caller along with the request.
+    pub fn route_guarded<H, F>(
+        self,
+        method: Method,
+        template: &str,
+        access: impl Into<Access>,
+        handler: H,
+    ) -> Self
+    where
+        H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_with_access(method, template, access.into(), authenticated(handler))
+    }
+
+    pub fn get_guarded<H, F>(self, template: &str, access: impl Into<Access>, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_guarded(Method::GET, template, access, handler)
+    }
+
+    pub fn post_guarded<H, F>(self, template: &str, access: impl Into<Access>, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_guarded(Method::POST, template, access, handler)
+    }
+
+    pub fn put_guarded<H, F>(self, template: &str, access: impl Into<Access>, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_guarded(Method::PUT, template, access, handler)
+    }
+
+    pub fn patch_guarded<H, F>(self, template: &str, access: impl Into<Access>, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_guarded(Method::PATCH, template, access, handler)
+    }
+
+    pub fn delete_guarded<H, F>(self, template: &str, access: impl Into<Access>, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_guarded(Method::DELETE, template, access, handler)
+    }
+}
+
+/// Hands the caller to the handler. Requests that reach it without an
+/// `auth_context` are answered with 401 instead.
+fn authenticated<H, F>(
+    handler: H,
+) -> impl Fn(RouteRequest, RequestContext) -> HandlerFuture + Send + Sync + 'static
+where
+    H: Fn(RouteRequest, RequestContext, AuthContext) -> F + Send + Sync + 'static,
+    F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+{
+    move |request, context| match context.auth_context.clone() {
+        Some(auth_context) => Box::pin(handler(request, context, auth_context)),
+        None => Box::pin(async {
+            AppError::Unauthorized(AUTHENTICATION_REQUIRED.to_owned()).into_response()
+        }),
+    }
+}
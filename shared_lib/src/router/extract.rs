@@ -0,0 +1,388 @@
+use std::{fmt, marker::PhantomData, str::FromStr};
+
+use aws_lambda_events::{apigw::ApiGatewayProxyResponse, http::StatusCode};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lambda_runtime::Error;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{
+    error::AppError,
+    utils::{cookie::parse_private_cookie, headers::get_header},
+};
+
+use super::request::RouteRequest;
+
+/// Largest request body `Json` accepts, after base64 decoding.
+pub const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Why a request could not be extracted. Returned from a route handler with
+/// `?`, the router answers it with the status, message and `code` below.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Rejection {
+    pub(crate) fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn into_response(self) -> Result<ApiGatewayProxyResponse, Error> {
+        AppError::Rejected(self).into_response()
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+pub trait FromRequest: Sized {
+    fn from_request(request: &RouteRequest) -> Result<Self, Rejection>;
+}
+
+/// A JSON request body. Requires an `application/json` content type.
+#[derive(Debug)]
+pub struct Json<T>(pub T);
+
+/// The query string, decoded from its string values.
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+/// Names the header a [`Header`] extracts, through a unit struct per header.
+pub trait HeaderName {
+    const NAME: &'static str;
+}
+
+/// The request header named by `N`, parsed with `FromStr`.
+pub struct Header<N, T = String>(pub T, pub PhantomData<N>);
+
+impl<N, T: fmt::Debug> fmt::Debug for Header<N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Header").field(&self.0).finish()
+    }
+}
+
+/// Names the private cookie a [`Cookie`] extracts. Cookie names are read
+/// from the environment, so unlike [`HeaderName`] this is a function.
+pub trait CookieName {
+    fn name() -> String;
+}
+
+/// The private cookie named by `N`, decrypted and parsed with `FromStr`.
+/// Extract `Option<Cookie<N, T>>` when the cookie may be absent.
+pub struct Cookie<N, T = String>(pub T, pub PhantomData<N>);
+
+impl<N, T: fmt::Debug> fmt::Debug for Cookie<N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Cookie").field(&self.0).finish()
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(request: &RouteRequest) -> Result<Self, Rejection> {
+        let content_type = get_header(&request.event, "content-type").unwrap_or_default();
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        if mime_type != "application/json" && !mime_type.ends_with("+json") {
+            return Err(Rejection::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "Expected an application/json request body",
+            ));
+        }
+
+        let payload = &request.event.payload;
+        let body = payload.body.clone().unwrap_or_default();
+
+        let body = if payload.is_base64_encoded {
+            STANDARD.decode(body.trim()).map_err(|_| {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_body_encoding",
+                    "Request body is not valid base64",
+                )
+            })?
+        } else {
+            body.into_bytes()
+        };
+
+        if body.len() > MAX_BODY_BYTES {
+            return Err(Rejection::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "body_too_large",
+                format!("Request body cannot be more than {} bytes", MAX_BODY_BYTES),
+            ));
+        }
+
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Err(Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "missing_body",
+                "Request body is required",
+            ));
+        }
+
+        serde_json::from_slice::<T>(&body)
+            .map(Json)
+            .map_err(|error| {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_json",
+                    format!("Invalid JSON body: {}", error),
+                )
+            })
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    fn from_request(request: &RouteRequest) -> Result<Self, Rejection> {
+        let query_params = request
+            .event
+            .payload
+            .query_string_parameters
+            .clone()
+            .filter(|query_params| !query_params.is_null())
+            .unwrap_or_else(|| json!({}));
+
+        serde_json::from_value::<T>(query_params)
+            .map(Query)
+            .map_err(|error| {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_query",
+                    format!("Invalid query string: {}", error),
+                )
+            })
+    }
+}
+
+impl<N: HeaderName, T: FromStr> FromRequest for Header<N, T> {
+    fn from_request(request: &RouteRequest) -> Result<Self, Rejection> {
+        let value = get_header(&request.event, N::NAME).ok_or_else(|| {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "missing_header",
+                format!("{} header is required", N::NAME),
+            )
+        })?;
+
+        value
+            .trim()
+            .parse::<T>()
+            .map(|value| Header(value, PhantomData))
+            .map_err(|_| {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_header",
+                    format!("{} header is invalid", N::NAME),
+                )
+            })
+    }
+}
+
+impl<N: CookieName, T: FromStr> Cookie<N, T> {
+    /// `None` if the cookie is absent or cannot be decrypted.
+    fn parse(request: &RouteRequest) -> Result<Option<Self>, Rejection> {
+        let name = N::name();
+
+        match parse_private_cookie(&request.event, &name) {
+            Some(value) => value
+                .parse::<T>()
+                .map(|value| Some(Cookie(value, PhantomData)))
+                .map_err(|_| {
+                    Rejection::new(
+                        StatusCode::BAD_REQUEST,
+                        "invalid_cookie",
+                        format!("{} cookie is invalid", name),
+                    )
+                }),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<N: CookieName, T: FromStr> FromRequest for Cookie<N, T> {
+    fn from_request(request: &RouteRequest) -> Result<Self, Rejection> {
+        Self::parse(request)?.ok_or_else(|| {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                "missing_cookie",
+                format!("{} cookie is required", N::name()),
+            )
+        })
+    }
+}
+
+impl<N: CookieName, T: FromStr> FromRequest for Option<Cookie<N, T>> {
+    fn from_request(request: &RouteRequest) -> Result<Self, Rejection> {
+        Cookie::parse(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lambda_runtime::{Context, LambdaEvent};
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{utils::deserialize::from_str_to_i64, RequestPayload};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Data {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Page {
+        #[serde(default, deserialize_with = "from_str_to_i64")]
+        current_page: i64,
+    }
+
+    fn request(payload: RequestPayload) -> RouteRequest {
+        RouteRequest::new(
+            LambdaEvent::new(payload, Context::default()),
+            HashMap::new(),
+        )
+    }
+
+    fn json_request(content_type: &str, body: &str) -> RouteRequest {
+        request(RequestPayload {
+            headers: Some(HashMap::from([(
+                "Content-Type".to_owned(),
+                Value::from(content_type),
+            )])),
+            body: Some(body.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn json_decodes_plain_and_base64_bodies() {
+        let Json(data) =
+            Json::<Data>::from_request(&json_request("application/json", r#"{"name":"ferris"}"#))
+                .unwrap();
+        assert_eq!(data.name, "ferris");
+
+        let mut request = json_request("application/json; charset=utf-8", "eyJuYW1lIjoiY3JhYiJ9");
+        request.event.payload.is_base64_encoded = true;
+
+        let Json(data) = Json::<Data>::from_request(&request).unwrap();
+        assert_eq!(data.name, "crab");
+    }
+
+    #[test]
+    fn json_rejects_wrong_content_type_and_malformed_bodies() {
+        let rejection =
+            Json::<Data>::from_request(&json_request("text/plain", r#"{"name":"ferris"}"#))
+                .unwrap_err();
+        assert_eq!(rejection.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let rejection = Json::<Data>::from_request(&json_request("application/json", "{\"name\":"))
+            .unwrap_err();
+        assert_eq!(rejection.code, "invalid_json");
+
+        let rejection =
+            Json::<Data>::from_request(&json_request("application/json", "")).unwrap_err();
+        assert_eq!(rejection.code, "missing_body");
+
+        let oversized = format!(r#"{{"name":"{}"}}"#, "a".repeat(MAX_BODY_BYTES));
+        let rejection =
+            Json::<Data>::from_request(&json_request("application/json", &oversized)).unwrap_err();
+        assert_eq!(rejection.status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn query_parses_string_values_and_rejects_bad_ones() {
+        let Query(page) = Query::<Page>::from_request(&request(RequestPayload::default())).unwrap();
+        assert_eq!(page.current_page, 0);
+
+        let Query(page) = Query::<Page>::from_request(&request(RequestPayload {
+            query_string_parameters: Some(json!({"current_page": "3"})),
+            ..Default::default()
+        }))
+        .unwrap();
+        assert_eq!(page.current_page, 3);
+
+        let rejection = Query::<Page>::from_request(&request(RequestPayload {
+            query_string_parameters: Some(json!({"current_page": "three"})),
+            ..Default::default()
+        }))
+        .unwrap_err();
+        assert_eq!(rejection.code, "invalid_query");
+
+        let rejection = Query::<Page>::from_request(&request(RequestPayload {
+            query_string_parameters: Some(json!({"current_page": ["1", "2"]})),
+            ..Default::default()
+        }))
+        .unwrap_err();
+        assert_eq!(rejection.code, "invalid_query");
+    }
+
+    struct ContentType;
+
+    impl HeaderName for ContentType {
+        const NAME: &'static str = "content-type";
+    }
+
+    struct RequestId;
+
+    impl HeaderName for RequestId {
+        const NAME: &'static str = "x-request-id";
+    }
+
+    #[test]
+    fn header_is_required_and_parsed() {
+        let request = json_request("application/json", "{}");
+
+        let Header(content_type, _) = request.extract::<Header<ContentType>>().unwrap();
+        assert_eq!(content_type, "application/json");
+
+        let rejection = request.extract::<Header<ContentType, u32>>().unwrap_err();
+        assert_eq!(rejection.code, "invalid_header");
+
+        let rejection = request.extract::<Header<RequestId>>().unwrap_err();
+        assert_eq!(rejection.code, "missing_header");
+        assert_eq!(rejection.message, "x-request-id header is required");
+    }
+
+    struct Session;
+
+    impl CookieName for Session {
+        fn name() -> String {
+            "session".to_owned()
+        }
+    }
+
+    #[test]
+    fn cookie_is_required_unless_optional() {
+        let request = request(RequestPayload::default());
+
+        let rejection = request.extract::<Cookie<Session>>().unwrap_err();
+        assert_eq!(rejection.code, "missing_cookie");
+        assert_eq!(rejection.message, "session cookie is required");
+
+        assert!(request
+            .extract::<Option<Cookie<Session>>>()
+            .unwrap()
+            .is_none());
+    }
+}
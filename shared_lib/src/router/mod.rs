@@ -0,0 +1,318 @@
+pub mod context;
+pub mod extract;
+pub mod path;
+pub mod request;
+
+use std::{collections::HashMap, future::Future};
+
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    http::{header::ALLOW, HeaderValue, Method},
+};
+use futures_util::future::BoxFuture;
+use lambda_runtime::{Error, LambdaEvent};
+
+use crate::{auth::permission::Permission, error::AppError, RequestPayload};
+
+use self::{path::PathTemplate, request::RouteRequest};
+
+pub type HandlerFuture = BoxFuture<'static, Result<ApiGatewayProxyResponse, Error>>;
+
+type BoxedHandler<S> = Box<dyn Fn(RouteRequest, S) -> HandlerFuture + Send + Sync>;
+
+struct Route<S> {
+    method: Method,
+    template: PathTemplate,
+    access: Access,
+    handler: BoxedHandler<S>,
+}
+
+/// Who may call a route, declared with it and checked once it is found,
+/// before its handler runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    /// Any caller who is signed in.
+    Authenticated,
+    /// Callers granted the permission.
+    Guarded(Permission),
+}
+
+impl From<Permission> for Access {
+    fn from(permission: Permission) -> Self {
+        Self::Guarded(permission)
+    }
+}
+
+/// Method and path template routing for a function. `S` is whatever the
+/// function builds per request for its handlers, such as the database handle.
+pub struct Router<S> {
+    routes: Vec<Route<S>>,
+}
+
+/// A route found for the request, ready to be called.
+pub struct MatchedRoute<'a, S> {
+    route: &'a Route<S>,
+    path_params: HashMap<String, String>,
+}
+
+/// Why no route was found, answered by [`RouteMiss::into_response`].
+/// Preflight `OPTIONS` requests never get here, as `CorsLayer` answers them.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RouteMiss {
+    NotFound,
+    MethodNotAllowed(Vec<Method>),
+}
+
+impl<S> Default for Router<S> {
+    fn default() -> Self {
+        Self { routes: Vec::new() }
+    }
+}
+
+impl<S> Router<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route<H, F>(self, method: Method, template: &str, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route_with_access(method, template, Access::Public, handler)
+    }
+
+    pub fn route_with_access<H, F>(
+        mut self,
+        method: Method,
+        template: &str,
+        access: Access,
+        handler: H,
+    ) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.routes.push(Route {
+            method,
+            template: PathTemplate::parse(template),
+            access,
+            handler: Box::new(move |request, state| Box::pin(handler(request, state))),
+        });
+
+        self
+    }
+
+    pub fn get<H, F>(self, template: &str, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route(Method::GET, template, handler)
+    }
+
+    pub fn post<H, F>(self, template: &str, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route(Method::POST, template, handler)
+    }
+
+    pub fn put<H, F>(self, template: &str, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route(Method::PUT, template, handler)
+    }
+
+    pub fn patch<H, F>(self, template: &str, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route(Method::PATCH, template, handler)
+    }
+
+    pub fn delete<H, F>(self, template: &str, handler: H) -> Self
+    where
+        H: Fn(RouteRequest, S) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        self.route(Method::DELETE, template, handler)
+    }
+
+    /// Every declared route's method, template and access, in declaration order.
+    pub fn declared_routes(&self) -> impl Iterator<Item = (&Method, &str, Access)> {
+        self.routes
+            .iter()
+            .map(|route| (&route.method, route.template.as_str(), route.access))
+    }
+
+    /// Finds the route for `method` and `path`. The first declared route
+    /// whose template matches wins.
+    pub fn find(&self, method: &Method, path: &str) -> Result<MatchedRoute<'_, S>, RouteMiss> {
+        let mut allowed_methods = Vec::new();
+
+        for route in &self.routes {
+            let Some(path_params) = route.template.matches(path) else {
+                continue;
+            };
+
+            if route.method == *method {
+                return Ok(MatchedRoute { route, path_params });
+            }
+
+            if !allowed_methods.contains(&route.method) {
+                allowed_methods.push(route.method.clone());
+            }
+        }
+
+        match allowed_methods.is_empty() {
+            true => Err(RouteMiss::NotFound),
+            false => Err(RouteMiss::MethodNotAllowed(allowed_methods)),
+        }
+    }
+
+    /// Routes and calls the handler in one go, for functions that need no
+    /// work between finding the route and running it.
+    pub async fn handle(
+        &self,
+        event: LambdaEvent<RequestPayload>,
+        state: S,
+    ) -> Result<ApiGatewayProxyResponse, Error> {
+        let method = request_method(&event);
+        let path = event.payload.path.clone().unwrap_or_default();
+
+        match self.find(&method, &path) {
+            Ok(route) => route.call(event, state).await,
+            Err(route_miss) => route_miss.into_response(),
+        }
+    }
+}
+
+impl<S> MatchedRoute<'_, S> {
+    pub fn method(&self) -> &Method {
+        &self.route.method
+    }
+
+    /// The template the route was declared with, e.g. `/api/blog/posts/:slug`.
+    pub fn template(&self) -> &str {
+        self.route.template.as_str()
+    }
+
+    pub fn access(&self) -> Access {
+        self.route.access
+    }
+
+    /// Runs the handler. Any error it returns, such as a [`extract::Rejection`] or an
+    /// [`AppError`], becomes the matching error response.
+    pub fn call(self, event: LambdaEvent<RequestPayload>, state: S) -> HandlerFuture {
+        let response = (self.route.handler)(RouteRequest::new(event, self.path_params), state);
+
+        Box::pin(async move {
+            match response.await {
+                Err(error) => AppError::from_boxed(error).into_response(),
+                response => response,
+            }
+        })
+    }
+}
+
+impl RouteMiss {
+    pub fn into_response(self) -> Result<ApiGatewayProxyResponse, Error> {
+        match self {
+            Self::NotFound => AppError::NotFound("Not found".to_owned()).into_response(),
+            Self::MethodNotAllowed(allowed_methods) => {
+                let mut response = AppError::MethodNotAllowed.into_response()?;
+
+                let allow = allowed_methods
+                    .iter()
+                    .map(Method::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let allow = HeaderValue::from_str(&allow)?;
+
+                response.headers.insert(ALLOW, allow.clone());
+                response.multi_value_headers.insert(ALLOW, allow);
+
+                Ok(response)
+            }
+        }
+    }
+}
+
+/// The request method, with anything unparseable treated as `GET`.
+pub fn request_method(event: &LambdaEvent<RequestPayload>) -> Method {
+    let http_method = event
+        .payload
+        .http_method
+        .clone()
+        .unwrap_or_default()
+        .to_uppercase();
+
+    Method::from_bytes(http_method.as_bytes()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::{encodings::Body, http::StatusCode};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::AppErrorResponse;
+
+    fn router() -> Router<()> {
+        Router::new()
+            .get("/api/blog/posts", |_, _| async {
+                AppErrorResponse::new(StatusCode::OK, None, None)
+            })
+            .get("/api/blog/posts/:slug", |_, _| async {
+                AppErrorResponse::new(StatusCode::OK, None, None)
+            })
+            .put("/api/blog/posts/:slug", |_, _| async {
+                AppErrorResponse::new(StatusCode::OK, None, None)
+            })
+    }
+
+    #[test]
+    fn finds_routes_by_method_and_template() {
+        let router = router();
+        let route = router.find(&Method::PUT, "/api/blog/posts/hello").unwrap();
+
+        assert_eq!(route.template(), "/api/blog/posts/:slug");
+        assert_eq!(route.method(), &Method::PUT);
+    }
+
+    #[test]
+    fn reports_unknown_paths_and_methods() {
+        let router = router();
+
+        assert_eq!(
+            router.find(&Method::GET, "/api/blog/tags").err(),
+            Some(RouteMiss::NotFound)
+        );
+        assert_eq!(
+            router.find(&Method::DELETE, "/api/blog/posts/hello").err(),
+            Some(RouteMiss::MethodNotAllowed(vec![Method::GET, Method::PUT]))
+        );
+    }
+
+    #[test]
+    fn method_not_allowed_lists_the_allowed_methods() {
+        let response = RouteMiss::MethodNotAllowed(vec![Method::GET, Method::PUT])
+            .into_response()
+            .unwrap();
+
+        assert_eq!(response.status_code, 405);
+        assert_eq!(response.headers.get(ALLOW).unwrap(), "GET, PUT");
+
+        let body = match response.body {
+            Some(Body::Text(body)) => serde_json::from_str::<Value>(&body).unwrap(),
+            body => panic!("unexpected body {:?}", body),
+        };
+        assert_eq!(body["data"]["code"], "method_not_allowed");
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use percent_encoding::percent_decode_str;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+/// A route path such as `/api/blog/posts/:slug`, where `:name` segments match
+/// any single non-empty path segment and capture it percent-decoded.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Self {
+        let segments = split_segments(template)
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => Segment::Param(name.to_owned()),
+                None => Segment::Literal(segment.to_owned()),
+            })
+            .collect();
+
+        Self {
+            template: template.to_owned(),
+            segments,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Returns the captured `:name` segments when `path` matches the template.
+    pub fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let mut path_segments = split_segments(path);
+        let mut params = HashMap::new();
+
+        for segment in &self.segments {
+            let path_segment = path_segments.next()?;
+
+            match segment {
+                Segment::Literal(literal) if literal == path_segment => {}
+                Segment::Literal(_) => return None,
+                // A segment that does not decode to UTF-8 matches nothing.
+                Segment::Param(name) => {
+                    let value = percent_decode_str(path_segment).decode_utf8().ok()?;
+                    params.insert(name.clone(), value.into_owned());
+                }
+            }
+        }
+
+        if path_segments.next().is_some() {
+            return None;
+        }
+
+        Some(params)
+    }
+}
+
+/// Empty segments are skipped so trailing and doubled slashes do not matter.
+fn split_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_templates_match_only_the_same_path() {
+        let template = PathTemplate::parse("/api/blog/posts");
+
+        assert_eq!(template.matches("/api/blog/posts"), Some(HashMap::new()));
+        assert_eq!(template.matches("/api/blog/posts/"), Some(HashMap::new()));
+        assert_eq!(template.matches("/api/blog"), None);
+        assert_eq!(template.matches("/api/blog/posts/rust"), None);
+    }
+
+    #[test]
+    fn params_capture_a_single_segment() {
+        let template = PathTemplate::parse("/api/blog/posts/:slug");
+        let params = template.matches("/api/blog/posts/hello-rust").unwrap();
+
+        assert_eq!(params.get("slug").map(String::as_str), Some("hello-rust"));
+        assert_eq!(template.matches("/api/blog/posts"), None);
+        assert_eq!(
+            template.matches("/api/blog/posts/hello-rust/comments"),
+            None
+        );
+    }
+
+    #[test]
+    fn params_are_percent_decoded() {
+        let template = PathTemplate::parse("/api/blog/posts/:slug");
+        let params = template
+            .matches("/api/blog/posts/caf%C3%A9%20au%2Flait")
+            .unwrap();
+
+        assert_eq!(params.get("slug").map(String::as_str), Some("café au/lait"));
+        assert_eq!(template.matches("/api/blog/posts/%FF"), None);
+    }
+}
@@ -0,0 +1,29 @@
+use std::{collections::HashMap, str::FromStr};
+
+use lambda_runtime::LambdaEvent;
+
+use crate::RequestPayload;
+
+use super::extract::{FromRequest, Rejection};
+
+/// The event handed to a route handler along with its captured path params.
+pub struct RouteRequest {
+    pub event: LambdaEvent<RequestPayload>,
+    path_params: HashMap<String, String>,
+}
+
+impl RouteRequest {
+    pub fn new(event: LambdaEvent<RequestPayload>, path_params: HashMap<String, String>) -> Self {
+        Self { event, path_params }
+    }
+
+    /// The `:name` segment of the route template, parsed as `T`.
+    pub fn path_param<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.path_params.get(name)?.parse::<T>().ok()
+    }
+
+    /// Runs an extractor such as `Json<T>` or `Query<T>` against the request.
+    pub fn extract<E: FromRequest>(&self) -> Result<E, Rejection> {
+        E::from_request(self)
+    }
+}
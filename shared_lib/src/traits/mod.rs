@@ -1 +1 @@
-pub mod model_traits;
\ No newline at end of file
+pub mod model_traits;
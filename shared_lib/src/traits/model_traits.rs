@@ -1,40 +1,63 @@
 use mongodb::{
-    bson::{document, Document},
-    results::InsertOneResult,
-    Database,
+    bson::document,
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Database, IndexModel,
 };
 
+use serde::de::DeserializeOwned;
+
 use crate::{DataInsertError, PaginatedData};
 
+pub use shared_lib_derive::Model;
+
 pub trait ModelTraits {
     fn save(
         &self,
         database: &Database,
     ) -> impl std::future::Future<Output = Result<InsertOneResult, DataInsertError>> + Send;
 
-    fn find(
+    /// Deserializes each document as `T`, the model itself or a struct
+    /// matching `projection`. A document that does not fit is an error.
+    fn find<T: DeserializeOwned + Send>(
         database: &Database,
         filter: document::Document,
         projection: Option<document::Document>,
         sort: Option<document::Document>,
         limit: i64,
-    ) -> impl std::future::Future<Output = mongodb::error::Result<Vec<Document>>> + Send;
+    ) -> impl std::future::Future<Output = mongodb::error::Result<Vec<T>>> + Send;
 
-    fn find_paginated(
+    fn find_paginated<T: DeserializeOwned + Send>(
         database: &Database,
         filter: document::Document,
         projection: Option<document::Document>,
         sort: Option<document::Document>,
         current_page: Option<i64>,
         items_per_page: Option<i64>,
-    ) -> impl std::future::Future<Output = mongodb::error::Result<PaginatedData>> + Send;
+    ) -> impl std::future::Future<Output = mongodb::error::Result<PaginatedData<T>>> + Send;
 
-    fn set_unique_fields(
-        database: &Database,
-    ) -> impl std::future::Future<Output = Result<(), DataInsertError>> + Send;
+    /// The indexes the collection needs, applied by the index registry rather
+    /// than on every insert.
+    fn indexes() -> Vec<IndexModel>;
 
     fn get_struct_name_as_plural_string() -> String;
 
+    fn update_one(
+        database: &Database,
+        filter: document::Document,
+        update: document::Document,
+    ) -> impl std::future::Future<Output = mongodb::error::Result<UpdateResult>> + Send;
+
+    fn update_many(
+        database: &Database,
+        filter: document::Document,
+        update: document::Document,
+    ) -> impl std::future::Future<Output = mongodb::error::Result<UpdateResult>> + Send;
+
+    fn delete_one(
+        database: &Database,
+        filter: document::Document,
+    ) -> impl std::future::Future<Output = mongodb::error::Result<DeleteResult>> + Send;
+
     fn count_documents(
         database: &Database,
         filter: document::Document,
@@ -1,46 +1,319 @@
-use std::{collections::HashMap, env};
+use std::{env, fmt};
 
-use cookie::{Cookie, CookieJar, Key};
+use aws_lambda_events::{
+    apigw::ApiGatewayProxyResponse,
+    http::{header::SET_COOKIE, HeaderValue},
+};
+use cookie::{time::Duration, Cookie, CookieJar, Key, SameSite};
 use lambda_runtime::LambdaEvent;
-use serde_json::Value;
 use validator::HasLen;
 
-use crate::RequestPayload;
+use crate::{router::extract::CookieName, utils::headers::get_header, RequestPayload};
+
+#[derive(Debug)]
+pub enum CookieKeyError {
+    Missing,
+    TooShort { position: usize, length: usize },
+}
+
+impl fmt::Display for CookieKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieKeyError::Missing => {
+                write!(f, "COOKIE_SECRETS (or COOKIE_SECRET) must contain a key")
+            }
+            CookieKeyError::TooShort { position, length } => write!(
+                f,
+                "Cookie key {} is {} bytes long, but at least 64 bytes are required",
+                position, length
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CookieKeyError {}
+
+/// Ordered cookie encryption keys.
+///
+/// The first key encrypts new cookies. The remaining keys are only used to
+/// decrypt cookies issued before a rotation.
+#[derive(Clone)]
+pub struct CookieKeys {
+    keys: Vec<Key>,
+}
+
+impl CookieKeys {
+    /// Reads the comma separated `COOKIE_SECRETS`, falling back to `COOKIE_SECRET`.
+    pub fn from_env() -> Result<Self, CookieKeyError> {
+        let secrets = env::var("COOKIE_SECRETS")
+            .ok()
+            .filter(|secrets| !secrets.trim().is_empty())
+            .unwrap_or(env::var("COOKIE_SECRET").unwrap_or_default());
+
+        Self::from_secrets(
+            secrets
+                .split(',')
+                .map(str::trim)
+                .filter(|secret| !secret.is_empty()),
+        )
+    }
+
+    pub fn from_secrets<'a>(
+        secrets: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, CookieKeyError> {
+        let keys = secrets
+            .into_iter()
+            .enumerate()
+            .map(|(index, secret)| {
+                Key::try_from(secret.as_bytes()).map_err(|_| CookieKeyError::TooShort {
+                    position: index + 1,
+                    length: secret.len(),
+                })
+            })
+            .collect::<Result<Vec<Key>, CookieKeyError>>()?;
+
+        if keys.is_empty() {
+            return Err(CookieKeyError::Missing);
+        }
+
+        Ok(Self { keys })
+    }
+
+    pub fn primary(&self) -> &Key {
+        &self.keys[0]
+    }
+
+    /// Encrypts `cookie` with the primary key and returns the `Set-Cookie` value.
+    pub fn encrypt(&self, cookie: Cookie<'static>) -> String {
+        let cookie_name = cookie.name().to_owned();
+
+        let mut jar = CookieJar::new();
+        jar.private_mut(self.primary()).add(cookie);
+
+        jar.get(&cookie_name)
+            .map(|cookie| cookie.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Decrypts the cookie `name`, also reporting whether it was sealed with a
+    /// rotated out key and should be re-issued.
+    pub fn decrypt(&self, jar: &CookieJar, name: &str) -> Option<(String, bool)> {
+        self.keys.iter().enumerate().find_map(|(index, key)| {
+            jar.private(key)
+                .get(name)
+                .map(|cookie| cookie.value().to_owned())
+                .filter(|value| value.length() > 0)
+                .map(|value| (value, index > 0))
+        })
+    }
+}
+
+/// Extracts the session cookie, holding the signed-in username.
+pub struct SessionCookie;
+
+impl CookieName for SessionCookie {
+    fn name() -> String {
+        env::var("COOKIE_NAME").unwrap_or_default()
+    }
+}
+
+/// The admin session cookie. Its value is encrypted by [`CookieKeys::encrypt`].
+pub fn session_cookie(username: String) -> Cookie<'static> {
+    let cookie_name = env::var("COOKIE_NAME").unwrap_or_default();
+
+    let mut cookie = Cookie::new(cookie_name, username);
+    cookie.set_http_only(true);
+    cookie.set_secure(true);
+    cookie.set_same_site(same_site_from_env());
+    cookie.set_path("/");
+    cookie.set_max_age(Duration::days(30));
+
+    cookie
+}
+
+/// `SameSite` policy from `COOKIE_SAME_SITE` (`strict`, `lax` or `none`), defaulting to `Lax`.
+pub fn same_site_from_env() -> SameSite {
+    match env::var("COOKIE_SAME_SITE")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => SameSite::Lax,
+    }
+}
 
 pub fn parse_cookie(event: &LambdaEvent<RequestPayload>) -> Option<String> {
-    let cookie_secret = env::var("COOKIE_SECRET").unwrap_or_default();
     let cookie_name = env::var("COOKIE_NAME").unwrap_or_default();
 
-    let default_headers_hash_map: HashMap<String, Value> = HashMap::new();
-    let cookie_string = if let Some(cookie) = event
-        .payload
+    parse_private_cookie(event, &cookie_name)
+}
+
+/// Decrypts the private cookie `name` from a `Cookie` header that may carry several cookies.
+pub fn parse_private_cookie(event: &LambdaEvent<RequestPayload>, name: &str) -> Option<String> {
+    let cookie_keys = CookieKeys::from_env().ok()?;
+
+    cookie_keys
+        .decrypt(&request_cookie_jar(event), name)
+        .map(|(value, _)| value)
+}
+
+/// Re-encrypts the cookie `name` under the primary key if it was sealed with
+/// a rotated out key. `rebuild` turns its value back into the cookie, with
+/// the attributes it was first issued with.
+pub fn reissue_cookie(
+    event: &LambdaEvent<RequestPayload>,
+    name: &str,
+    rebuild: impl FnOnce(String) -> Option<Cookie<'static>>,
+) -> Option<HeaderValue> {
+    let cookie_keys = CookieKeys::from_env().ok()?;
+
+    match cookie_keys.decrypt(&request_cookie_jar(event), name) {
+        Some((value, true)) => HeaderValue::from_str(&cookie_keys.encrypt(rebuild(value)?)).ok(),
+        _ => None,
+    }
+}
+
+/// Adds re-issued cookies to the response, except those the response already
+/// sets itself.
+pub fn append_reissued_cookies(
+    response: &mut ApiGatewayProxyResponse,
+    reissued_cookies: Vec<HeaderValue>,
+) {
+    let set_cookie_names = response
         .headers
-        .as_ref()
-        .unwrap_or(&default_headers_hash_map)
-        .get("cookie")
-    {
-        cookie.as_str().unwrap_or_default().to_owned()
-    } else {
-        "".to_owned()
-    };
-
-    match Cookie::parse(cookie_string) {
-        Ok(token) => {
-            let mut jar = CookieJar::new();
-            jar.add_original(token);
-            let key = Key::from(cookie_secret.as_bytes());
-            let cookie = jar
-                .private(&key)
-                .get(&cookie_name)
-                .unwrap_or_else(|| Cookie::new("", ""));
-            let session_token = cookie.value();
-
-            if session_token.length() > 0 {
-                return Some(session_token.to_owned());
-            } else {
-                return None;
-            }
+        .get_all(SET_COOKIE)
+        .iter()
+        .chain(response.multi_value_headers.get_all(SET_COOKIE))
+        .filter_map(set_cookie_name)
+        .collect::<Vec<_>>();
+
+    for reissued_cookie in reissued_cookies {
+        if set_cookie_name(&reissued_cookie).is_some_and(|name| set_cookie_names.contains(&name)) {
+            continue;
+        }
+
+        // Like the handlers, one cookie goes in `headers` and the rest in
+        // `multi_value_headers`.
+        if response.headers.contains_key(SET_COOKIE) {
+            response
+                .multi_value_headers
+                .append(SET_COOKIE, reissued_cookie);
+        } else {
+            response.headers.insert(SET_COOKIE, reissued_cookie);
         }
-        Err(_) => return None,
+    }
+}
+
+fn set_cookie_name(set_cookie: &HeaderValue) -> Option<String> {
+    let set_cookie = set_cookie.to_str().ok()?;
+
+    Cookie::parse(set_cookie)
+        .ok()
+        .map(|cookie| cookie.name().to_owned())
+}
+
+fn request_cookie_jar(event: &LambdaEvent<RequestPayload>) -> CookieJar {
+    let cookie_string = get_header(event, "cookie").unwrap_or_default();
+
+    let mut jar = CookieJar::new();
+
+    for cookie in Cookie::split_parse(cookie_string).flatten() {
+        jar.add_original(cookie.into_owned());
+    }
+
+    jar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_SECRET: &str = "old-secret-old-secret-old-secret-old-secret-old-secret-old-secret";
+    const NEW_SECRET: &str = "new-secret-new-secret-new-secret-new-secret-new-secret-new-secret";
+
+    fn jar_with(set_cookie: &str) -> CookieJar {
+        let mut jar = CookieJar::new();
+        jar.add_original(Cookie::parse(set_cookie.to_owned()).unwrap());
+        jar
+    }
+
+    #[test]
+    fn rotated_keys_still_decrypt_and_flag_reissue() {
+        let old_keys = CookieKeys::from_secrets([OLD_SECRET]).unwrap();
+        let rotated_keys = CookieKeys::from_secrets([NEW_SECRET, OLD_SECRET]).unwrap();
+
+        let old_cookie = old_keys.encrypt(Cookie::new("session", "admin"));
+        let new_cookie = rotated_keys.encrypt(Cookie::new("session", "admin"));
+
+        assert_eq!(
+            rotated_keys.decrypt(&jar_with(&old_cookie), "session"),
+            Some(("admin".to_owned(), true))
+        );
+        assert_eq!(
+            rotated_keys.decrypt(&jar_with(&new_cookie), "session"),
+            Some(("admin".to_owned(), false))
+        );
+        assert_eq!(old_keys.decrypt(&jar_with(&new_cookie), "session"), None);
+    }
+
+    #[test]
+    fn short_or_missing_keys_are_rejected() {
+        assert!(matches!(
+            CookieKeys::from_secrets([NEW_SECRET, "short"]),
+            Err(CookieKeyError::TooShort {
+                position: 2,
+                length: 5
+            })
+        ));
+        assert!(matches!(
+            CookieKeys::from_secrets([]),
+            Err(CookieKeyError::Missing)
+        ));
+    }
+
+    #[test]
+    fn the_cookie_header_is_read_whatever_its_case() {
+        let event = LambdaEvent::new(
+            RequestPayload {
+                headers: Some(
+                    [("Cookie".to_owned(), "session=admin; other=1".into())]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            Default::default(),
+        );
+
+        let jar = request_cookie_jar(&event);
+        assert_eq!(jar.get("session").map(Cookie::value), Some("admin"));
+    }
+
+    #[test]
+    fn reissued_cookies_skip_the_ones_the_response_sets() {
+        let mut response = ApiGatewayProxyResponse::default();
+        response
+            .headers
+            .insert(SET_COOKIE, HeaderValue::from_static("session=new"));
+
+        append_reissued_cookies(
+            &mut response,
+            vec![
+                HeaderValue::from_static("session=reissued"),
+                HeaderValue::from_static("csrf=reissued"),
+            ],
+        );
+
+        assert_eq!(response.headers.get(SET_COOKIE).unwrap(), "session=new");
+        assert_eq!(
+            response
+                .multi_value_headers
+                .get_all(SET_COOKIE)
+                .iter()
+                .collect::<Vec<_>>(),
+            ["csrf=reissued"]
+        );
     }
 }
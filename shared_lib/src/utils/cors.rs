@@ -7,11 +7,10 @@ use aws_lambda_events::{
 };
 use lambda_runtime::Error;
 
-pub fn cors() -> Result<ApiGatewayProxyResponse, Error> {
+/// The CORS headers every response to the frontend carries.
+pub fn cors_headers() -> HeaderMap {
     let frontend_base_url = env::var("FRONTEND_BASE_URL").unwrap_or_default();
 
-    let status_as_i64: i64 = StatusCode::OK.as_u16() as i64;
-
     let mut headers = HeaderMap::new();
     // headers.insert("content-type", "application/json".parse().unwrap());
     headers.insert(
@@ -20,15 +19,29 @@ pub fn cors() -> Result<ApiGatewayProxyResponse, Error> {
     );
     headers.insert(
         "Access-Control-Allow-Methods",
-        "POST, GET, OPTIONS".parse().unwrap(),
+        "POST, GET, PUT, PATCH, OPTIONS".parse().unwrap(),
     );
     headers.insert(
         "Access-Control-Allow-Headers",
-        "Origin, X-Requested-With, Content-Type, Accept"
+        "Origin, X-Requested-With, Content-Type, Accept, Authorization, X-CSRF-Token, X-Request-Id"
             .parse()
             .unwrap(),
     );
     headers.insert("Access-Control-Allow-Credentials", "true".parse().unwrap());
+    headers.insert(
+        "Access-Control-Expose-Headers",
+        "X-Request-Id, X-Impersonated-By, X-Next-Cursor"
+            .parse()
+            .unwrap(),
+    );
+
+    headers
+}
+
+pub fn cors() -> Result<ApiGatewayProxyResponse, Error> {
+    let status_as_i64: i64 = StatusCode::OK.as_u16() as i64;
+
+    let headers = cors_headers();
 
     // Preflight request. Reply successfully:
     Ok(ApiGatewayProxyResponse {
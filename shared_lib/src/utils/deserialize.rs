@@ -0,0 +1,21 @@
+use serde::{de::Error as SerdeError, Deserialize, Deserializer};
+
+/// `deserialize_with` helper for query params, which arrive as strings. Pair
+/// it with `#[serde(default)]` for params that may be left out.
+pub fn from_str_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+
+    s.parse::<bool>().map_err(SerdeError::custom)
+}
+
+pub fn from_str_to_i64<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+
+    s.parse::<i64>().map_err(SerdeError::custom)
+}
@@ -0,0 +1,53 @@
+use lambda_runtime::LambdaEvent;
+
+use crate::RequestPayload;
+
+/// Looks up a request header by name, ignoring case.
+pub fn get_header(event: &LambdaEvent<RequestPayload>, name: &str) -> Option<String> {
+    event
+        .payload
+        .headers
+        .as_ref()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .and_then(|(_, value)| value.as_str())
+        .map(|value| value.to_owned())
+}
+
+/// Returns the token from an `Authorization: Bearer <token>` header.
+pub fn parse_bearer_token(event: &LambdaEvent<RequestPayload>) -> Option<String> {
+    let authorization = get_header(event, "authorization")?;
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+        return None;
+    }
+
+    Some(token.trim().to_owned())
+}
+
+/// Who sent a request, as far as the proxy headers tell.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetadata {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl RequestMetadata {
+    pub fn from_event(event: &LambdaEvent<RequestPayload>) -> Self {
+        let ip_address = get_header(event, "x-nf-client-connection-ip").or_else(|| {
+            get_header(event, "x-forwarded-for").and_then(|forwarded_for| {
+                forwarded_for
+                    .split(',')
+                    .next()
+                    .map(str::trim)
+                    .map(str::to_owned)
+            })
+        });
+
+        Self {
+            ip_address,
+            user_agent: get_header(event, "user-agent"),
+        }
+    }
+}
@@ -1,2 +1,8 @@
+pub mod cookie;
 pub mod cors;
-pub mod cookie;
\ No newline at end of file
+pub mod deserialize;
+pub mod headers;
+pub mod password_hasher;
+pub mod password_policy;
+pub mod signed_token;
+pub mod token;
@@ -0,0 +1,224 @@
+use std::{env, fmt};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, SaltString},
+    Algorithm, Argon2, Params, PasswordHasher as _, PasswordVerifier, Version,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasswordHashAlgorithm {
+    Bcrypt,
+    Argon2id,
+}
+
+#[derive(Debug)]
+pub enum PasswordHashError {
+    BcryptError(bcrypt::BcryptError),
+    Argon2Error(String),
+}
+
+impl fmt::Display for PasswordHashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordHashError::BcryptError(error) => write!(f, "bcrypt error: {}", error),
+            PasswordHashError::Argon2Error(error) => write!(f, "argon2 error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PasswordHashError {}
+
+impl From<bcrypt::BcryptError> for PasswordHashError {
+    fn from(error: bcrypt::BcryptError) -> Self {
+        PasswordHashError::BcryptError(error)
+    }
+}
+
+/// Hashes new passwords with the preferred algorithm and verifies hashes
+/// produced by any supported algorithm or parameters.
+#[derive(Debug, Clone)]
+pub struct PasswordHasher {
+    pub algorithm: PasswordHashAlgorithm,
+    pub bcrypt_cost: u32,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+}
+
+impl Default for PasswordHasher {
+    /// Argon2id with the OWASP recommended minimum parameters.
+    fn default() -> Self {
+        Self {
+            algorithm: PasswordHashAlgorithm::Argon2id,
+            bcrypt_cost: 12,
+            argon2_memory_kib: 19456,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+        }
+    }
+}
+
+impl PasswordHasher {
+    pub fn from_env() -> Self {
+        let default_hasher = Self::default();
+
+        let number = |name: &str, default: u32| {
+            env::var(name)
+                .unwrap_or_default()
+                .parse::<u32>()
+                .unwrap_or(default)
+        };
+
+        let algorithm = match env::var("PASSWORD_HASH_ALGORITHM")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "bcrypt" => PasswordHashAlgorithm::Bcrypt,
+            _ => PasswordHashAlgorithm::Argon2id,
+        };
+
+        Self {
+            algorithm,
+            bcrypt_cost: number("BCRYPT_COST", default_hasher.bcrypt_cost),
+            argon2_memory_kib: number("ARGON2_MEMORY_KIB", default_hasher.argon2_memory_kib),
+            argon2_iterations: number("ARGON2_ITERATIONS", default_hasher.argon2_iterations),
+            argon2_parallelism: number("ARGON2_PARALLELISM", default_hasher.argon2_parallelism),
+        }
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, PasswordHashError> {
+        match self.algorithm {
+            PasswordHashAlgorithm::Bcrypt => Ok(bcrypt::hash(password, self.bcrypt_cost)?),
+            PasswordHashAlgorithm::Argon2id => {
+                let salt = SaltString::generate(&mut OsRng);
+
+                self.argon2()?
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|password_hash| password_hash.to_string())
+                    .map_err(|error| PasswordHashError::Argon2Error(error.to_string()))
+            }
+        }
+    }
+
+    /// Verifies `password` against a bcrypt or Argon2 hash, whatever the current preference.
+    pub fn verify(&self, password: &str, password_hash: &str) -> bool {
+        match algorithm_of(password_hash) {
+            Some(PasswordHashAlgorithm::Bcrypt) => {
+                bcrypt::verify(password, password_hash).unwrap_or_default()
+            }
+            Some(PasswordHashAlgorithm::Argon2id) => match PasswordHash::new(password_hash) {
+                // Parameters are read from the hash itself.
+                Ok(parsed_hash) => Argon2::default()
+                    .verify_password(password.as_bytes(), &parsed_hash)
+                    .is_ok(),
+                Err(_) => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Whether `password_hash` was produced with another algorithm or other parameters
+    /// than the preferred ones and should be replaced after a successful login.
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        if algorithm_of(password_hash) != Some(self.algorithm.clone()) {
+            return true;
+        }
+
+        match self.algorithm {
+            PasswordHashAlgorithm::Bcrypt => password_hash
+                .split('$')
+                .nth(2)
+                .and_then(|cost| cost.parse::<u32>().ok())
+                .map(|cost| cost != self.bcrypt_cost)
+                .unwrap_or(true),
+            PasswordHashAlgorithm::Argon2id => match PasswordHash::new(password_hash) {
+                Ok(parsed_hash) => match Params::try_from(&parsed_hash) {
+                    Ok(params) => {
+                        parsed_hash.version != Some(Version::V0x13 as u32)
+                            || params.m_cost() != self.argon2_memory_kib
+                            || params.t_cost() != self.argon2_iterations
+                            || params.p_cost() != self.argon2_parallelism
+                    }
+                    Err(_) => true,
+                },
+                Err(_) => true,
+            },
+        }
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, PasswordHashError> {
+        let params = Params::new(
+            self.argon2_memory_kib,
+            self.argon2_iterations,
+            self.argon2_parallelism,
+            None,
+        )
+        .map_err(|error| PasswordHashError::Argon2Error(error.to_string()))?;
+
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+fn algorithm_of(password_hash: &str) -> Option<PasswordHashAlgorithm> {
+    if password_hash.starts_with("$argon2id$") {
+        Some(PasswordHashAlgorithm::Argon2id)
+    } else if ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| password_hash.starts_with(prefix))
+    {
+        Some(PasswordHashAlgorithm::Bcrypt)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters keep the tests fast.
+    fn argon2_hasher() -> PasswordHasher {
+        PasswordHasher {
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            ..Default::default()
+        }
+    }
+
+    fn bcrypt_hasher(cost: u32) -> PasswordHasher {
+        PasswordHasher {
+            algorithm: PasswordHashAlgorithm::Bcrypt,
+            bcrypt_cost: cost,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn verifies_hashes_from_either_algorithm() {
+        let bcrypt_hash = bcrypt_hasher(4).hash("Correct4HorseBattery").unwrap();
+        let argon2_hash = argon2_hasher().hash("Correct4HorseBattery").unwrap();
+
+        for password_hash in [&bcrypt_hash, &argon2_hash] {
+            assert!(argon2_hasher().verify("Correct4HorseBattery", password_hash));
+            assert!(!argon2_hasher().verify("wrong password", password_hash));
+        }
+    }
+
+    #[test]
+    fn flags_outdated_hashes_for_rehash() {
+        let bcrypt_hash = bcrypt_hasher(4).hash("Correct4HorseBattery").unwrap();
+        let argon2_hash = argon2_hasher().hash("Correct4HorseBattery").unwrap();
+
+        assert!(!bcrypt_hasher(4).needs_rehash(&bcrypt_hash));
+        assert!(bcrypt_hasher(5).needs_rehash(&bcrypt_hash));
+        assert!(argon2_hasher().needs_rehash(&bcrypt_hash));
+        assert!(!argon2_hasher().needs_rehash(&argon2_hash));
+
+        let stronger_hasher = PasswordHasher {
+            argon2_iterations: 2,
+            ..argon2_hasher()
+        };
+        assert!(stronger_hasher.needs_rehash(&argon2_hash));
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    borrow::Cow,
+    env,
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use validator::{Validate, ValidationError};
+
+/// Password rules read from the environment.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Self {
+        let default_policy = Self::default();
+
+        let flag = |name: &str, default: bool| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.to_lowercase().parse::<bool>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .unwrap_or_default()
+                .parse::<usize>()
+                .unwrap_or(default_policy.min_length),
+            require_lowercase: flag(
+                "PASSWORD_REQUIRE_LOWERCASE",
+                default_policy.require_lowercase,
+            ),
+            require_uppercase: flag(
+                "PASSWORD_REQUIRE_UPPERCASE",
+                default_policy.require_uppercase,
+            ),
+            require_digit: flag("PASSWORD_REQUIRE_DIGIT", default_policy.require_digit),
+            require_symbol: flag("PASSWORD_REQUIRE_SYMBOL", default_policy.require_symbol),
+        }
+    }
+
+    /// Checks length and character classes.
+    pub fn check(&self, password: &str) -> Result<(), ValidationError> {
+        if password.chars().count() < self.min_length {
+            return Err(policy_error(
+                "password_too_short",
+                format!(
+                    "Password must be at least {} characters long",
+                    self.min_length
+                ),
+            ));
+        }
+
+        let character_classes = [
+            (
+                self.require_lowercase,
+                password.chars().any(char::is_lowercase),
+                "a lowercase letter",
+            ),
+            (
+                self.require_uppercase,
+                password.chars().any(char::is_uppercase),
+                "an uppercase letter",
+            ),
+            (
+                self.require_digit,
+                password.chars().any(|c| c.is_ascii_digit()),
+                "a digit",
+            ),
+            (
+                self.require_symbol,
+                password.chars().any(|c| !c.is_alphanumeric()),
+                "a symbol",
+            ),
+        ];
+
+        for (required, present, description) in character_classes {
+            if required && !present {
+                return Err(policy_error(
+                    "password_missing_character_class",
+                    format!("Password must contain {}", description),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A plain text password together with the account details it must not contain.
+#[derive(Debug, Serialize, Deserialize, Validate, Clone, Default)]
+#[validate(schema(
+    function = "validate_password_not_personal",
+    skip_on_field_errors = false
+))]
+pub struct NewPassword {
+    #[validate(custom = "validate_password")]
+    pub password: String,
+    pub username: Option<String>,
+    pub email: Option<String>,
+}
+
+/// `validator` custom validator applying the configured policy and breach list.
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    PasswordPolicy::from_env().check(password)?;
+
+    if is_breached_password(password) {
+        return Err(policy_error(
+            "password_breached",
+            "This password has appeared in a data breach. Choose a different one".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Rejects passwords containing the username or the local part of the email.
+pub fn validate_password_not_personal(new_password: &NewPassword) -> Result<(), ValidationError> {
+    let password = new_password.password.to_lowercase();
+
+    let email_local_part = new_password
+        .email
+        .as_deref()
+        .and_then(|email| email.split('@').next());
+
+    let is_personal = [new_password.username.as_deref(), email_local_part]
+        .into_iter()
+        .flatten()
+        .map(str::to_lowercase)
+        // Very short identifiers would reject too many unrelated passwords.
+        .filter(|identifier| identifier.chars().count() >= 3)
+        .any(|identifier| password.contains(&identifier));
+
+    if is_personal {
+        return Err(policy_error(
+            "password_contains_personal_data",
+            "Password must not contain your username or email address".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Looks the password up in the file at `BREACHED_PASSWORDS_FILE`, if configured.
+///
+/// The file holds uppercase SHA-1 hashes, one `HASH[:COUNT]` per line, as
+/// produced by concatenating Pwned Passwords k-anonymity range responses with
+/// their five character prefix prepended. Lines are compared by prefix first,
+/// exactly like a range query, so the full hash never needs to leave this module.
+pub fn is_breached_password(password: &str) -> bool {
+    let breached_passwords_file = match env::var("BREACHED_PASSWORDS_FILE") {
+        Ok(path) if !path.is_empty() => path,
+        _ => return false,
+    };
+
+    let file = match File::open(&breached_passwords_file) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!(
+                "Could not open breached passwords file {}: {}",
+                breached_passwords_file, error
+            );
+            return false;
+        }
+    };
+
+    let password_hash: String = Sha1::digest(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    let (prefix, suffix) = password_hash.split_at(5);
+
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| line.len() >= 40 && line[..5].eq_ignore_ascii_case(prefix))
+        .any(|line| {
+            let hash = line.split(':').next().unwrap_or_default().trim();
+            hash.len() == 40 && hash[5..].eq_ignore_ascii_case(suffix)
+        })
+}
+
+fn policy_error(code: &'static str, message: String) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::from(message));
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_length_and_character_classes() {
+        let policy = PasswordPolicy::default();
+
+        assert!(policy.check("Sh0rt").is_err());
+        assert!(policy.check("alllowercase123").is_err());
+        assert!(policy.check("NoDigitsAtAllHere").is_err());
+        assert!(policy.check("Correct4HorseBattery").is_ok());
+    }
+
+    #[test]
+    fn rejects_passwords_containing_username_or_email() {
+        let new_password = |password: &str| NewPassword {
+            password: password.to_owned(),
+            username: Some("Clarnx".to_owned()),
+            email: Some("writer@example.com".to_owned()),
+        };
+
+        assert!(validate_password_not_personal(&new_password("MyNameIsClarnx1")).is_err());
+        assert!(validate_password_not_personal(&new_password("Writer2024Rocks")).is_err());
+        assert!(validate_password_not_personal(&new_password("Correct4HorseBattery")).is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SignedTokenClaims {
+    pub subject: String,
+    pub purpose: String,
+    pub expires_at: i64,
+}
+
+/// Creates a URL safe token of the form `<claims>.<signature>` where the
+/// signature is an HMAC-SHA256 of the encoded claims. Returns `None` for an
+/// empty secret, which would let anyone forge tokens.
+pub fn create_signed_token(
+    subject: &str,
+    purpose: &str,
+    ttl: Duration,
+    secret: &str,
+) -> Option<String> {
+    if secret.is_empty() {
+        return None;
+    }
+
+    let claims = SignedTokenClaims {
+        subject: subject.to_owned(),
+        purpose: purpose.to_owned(),
+        expires_at: (Utc::now() + ttl).timestamp(),
+    };
+
+    let encoded_claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap_or_default());
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(encoded_claims.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+    Some(format!("{}.{}", encoded_claims, signature))
+}
+
+/// Returns the token subject if the signature is valid, the purpose matches
+/// and the token has not expired. Nothing verifies against an empty secret.
+pub fn verify_signed_token(token: &str, purpose: &str, secret: &str) -> Option<String> {
+    if secret.is_empty() {
+        return None;
+    }
+
+    let (encoded_claims, encoded_signature) = token.split_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(encoded_signature).ok()?;
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(encoded_claims.as_bytes());
+    mac.verify_slice(&signature).ok()?;
+
+    let claims_bytes = URL_SAFE_NO_PAD.decode(encoded_claims).ok()?;
+    let claims = serde_json::from_slice::<SignedTokenClaims>(&claims_bytes).ok()?;
+
+    if claims.purpose != purpose || claims.expires_at < Utc::now().timestamp() {
+        return None;
+    }
+
+    Some(claims.subject)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+    const PURPOSE: &str = "email_verification";
+
+    fn token(ttl: Duration) -> String {
+        create_signed_token("user@example.com", PURPOSE, ttl, SECRET).unwrap()
+    }
+
+    #[test]
+    fn valid_token_returns_its_subject() {
+        assert_eq!(
+            verify_signed_token(&token(Duration::hours(1)), PURPOSE, SECRET),
+            Some("user@example.com".to_owned())
+        );
+    }
+
+    #[test]
+    fn empty_secret_neither_signs_nor_verifies() {
+        assert_eq!(
+            create_signed_token("user@example.com", PURPOSE, Duration::hours(1), ""),
+            None
+        );
+        assert_eq!(
+            verify_signed_token(&token(Duration::hours(1)), PURPOSE, ""),
+            None
+        );
+    }
+
+    #[test]
+    fn bad_signature_is_rejected() {
+        let token = token(Duration::hours(1));
+
+        assert_eq!(verify_signed_token(&token, PURPOSE, "other-secret"), None);
+
+        let (claims, _) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", claims, URL_SAFE_NO_PAD.encode(b"forged"));
+        assert_eq!(verify_signed_token(&forged, PURPOSE, SECRET), None);
+    }
+
+    #[test]
+    fn wrong_purpose_is_rejected() {
+        assert_eq!(
+            verify_signed_token(&token(Duration::hours(1)), "password_reset", SECRET),
+            None
+        );
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        assert_eq!(
+            verify_signed_token(&token(Duration::hours(-1)), PURPOSE, SECRET),
+            None
+        );
+    }
+
+    #[test]
+    fn malformed_token_is_rejected() {
+        for token in ["", "no-separator", "not base64!.signature", "e30.e30"] {
+            assert_eq!(
+                verify_signed_token(token, PURPOSE, SECRET),
+                None,
+                "{}",
+                token
+            );
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Generates a random, URL safe token with 256 bits of entropy.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hashes a token for storage so the plain value never reaches the database.
+pub fn hash_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+/// Generates a short lowercase hex identifier, e.g. for API key prefixes.
+pub fn generate_identifier(length: usize) -> String {
+    let mut bytes = vec![0u8; length.div_ceil(2)];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    let identifier: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    identifier[..length].to_owned()
+}
@@ -0,0 +1,449 @@
+//! `#[derive(Model)]`, which implements `shared_lib`'s `ModelTraits` for a
+//! struct stored in its own collection.
+
+use inflector::Inflector;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, Data, DeriveInput, Fields, LitInt, LitStr, Token,
+};
+
+/// Implements `ModelTraits` on top of `shared_lib::database::queries`.
+///
+/// On the struct:
+/// - `#[model(collection = "name")]` overrides the collection, which is
+///   otherwise the lowercased, pluralised struct name (`RefreshToken` is
+///   stored in `refreshtokens`).
+/// - `#[model(timestamps)]` fills a missing `created_at` and `updated_at` on
+///   `save` and sets `updated_at` on every update. Both fields must be
+///   `Option<DateTime<Utc>>`.
+/// - `#[model(index(user_id = 1, created_at = -1))]` declares a compound
+///   index; repeat it for several.
+/// - `#[model(append_only)]` makes updates and deletes fail.
+///
+/// On a field:
+/// - `#[model(unique)]` declares a unique index on it.
+/// - `#[model(text)]` adds it to the collection's text index.
+/// - `#[model(ttl = 0)]` expires documents that many seconds past the date
+///   in the field, which has to hold a BSON date.
+///
+/// `save` validates the struct first, so it also has to derive `Validate`.
+/// The model is added to `shared_lib::database::indexes::registry`. The
+/// recorded index version is a hash of every registered spec, so new or
+/// changed indexes are created on the next cold start.
+#[proc_macro_derive(Model, attributes(model))]
+pub fn derive_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ModelOptions {
+    collection: Option<String>,
+    timestamps: bool,
+    append_only: bool,
+    compound_indexes: Vec<Vec<(String, i32)>>,
+    unique_fields: Vec<String>,
+    text_fields: Vec<String>,
+    ttl_fields: Vec<(String, u64)>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let options = parse_options(&input)?;
+    let name = &input.ident;
+
+    let collection = options
+        .collection
+        .clone()
+        .unwrap_or_else(|| name.to_string().to_lowercase().to_plural());
+
+    let indexes = index_tokens(&options);
+    let save = save_tokens(&options);
+    let (update_one, update_many, delete_one) = write_tokens(&options);
+
+    Ok(quote! {
+        impl ::shared_lib::traits::model_traits::ModelTraits for #name {
+            fn get_struct_name_as_plural_string() -> ::std::string::String {
+                #collection.to_owned()
+            }
+
+            fn indexes() -> ::std::vec::Vec<::shared_lib::__private::mongodb::IndexModel> {
+                ::std::vec![#(#indexes),*]
+            }
+
+            async fn save(
+                &self,
+                database: &::shared_lib::__private::mongodb::Database,
+            ) -> ::std::result::Result<
+                ::shared_lib::__private::mongodb::results::InsertOneResult,
+                ::shared_lib::DataInsertError,
+            > {
+                #save
+            }
+
+            async fn find<T: ::shared_lib::__private::serde::de::DeserializeOwned + Send>(
+                database: &::shared_lib::__private::mongodb::Database,
+                filter: ::shared_lib::__private::mongodb::bson::Document,
+                projection: ::std::option::Option<::shared_lib::__private::mongodb::bson::Document>,
+                sort: ::std::option::Option<::shared_lib::__private::mongodb::bson::Document>,
+                limit: i64,
+            ) -> ::shared_lib::__private::mongodb::error::Result<::std::vec::Vec<T>> {
+                ::shared_lib::database::queries::find::<Self, T>(
+                    database, filter, projection, sort, limit,
+                )
+                .await
+            }
+
+            async fn find_paginated<T: ::shared_lib::__private::serde::de::DeserializeOwned + Send>(
+                database: &::shared_lib::__private::mongodb::Database,
+                filter: ::shared_lib::__private::mongodb::bson::Document,
+                projection: ::std::option::Option<::shared_lib::__private::mongodb::bson::Document>,
+                sort: ::std::option::Option<::shared_lib::__private::mongodb::bson::Document>,
+                current_page: ::std::option::Option<i64>,
+                items_per_page: ::std::option::Option<i64>,
+            ) -> ::shared_lib::__private::mongodb::error::Result<::shared_lib::PaginatedData<T>> {
+                ::shared_lib::database::queries::find_paginated::<Self, T>(
+                    database, filter, projection, sort, current_page, items_per_page,
+                )
+                .await
+            }
+
+            async fn update_one(
+                database: &::shared_lib::__private::mongodb::Database,
+                filter: ::shared_lib::__private::mongodb::bson::Document,
+                update: ::shared_lib::__private::mongodb::bson::Document,
+            ) -> ::shared_lib::__private::mongodb::error::Result<
+                ::shared_lib::__private::mongodb::results::UpdateResult,
+            > {
+                #update_one
+            }
+
+            async fn update_many(
+                database: &::shared_lib::__private::mongodb::Database,
+                filter: ::shared_lib::__private::mongodb::bson::Document,
+                update: ::shared_lib::__private::mongodb::bson::Document,
+            ) -> ::shared_lib::__private::mongodb::error::Result<
+                ::shared_lib::__private::mongodb::results::UpdateResult,
+            > {
+                #update_many
+            }
+
+            async fn delete_one(
+                database: &::shared_lib::__private::mongodb::Database,
+                filter: ::shared_lib::__private::mongodb::bson::Document,
+            ) -> ::shared_lib::__private::mongodb::error::Result<
+                ::shared_lib::__private::mongodb::results::DeleteResult,
+            > {
+                #delete_one
+            }
+
+            async fn count_documents(
+                database: &::shared_lib::__private::mongodb::Database,
+                filter: ::shared_lib::__private::mongodb::bson::Document,
+            ) -> ::shared_lib::__private::mongodb::error::Result<u64> {
+                ::shared_lib::database::queries::count_documents::<Self>(database, filter).await
+            }
+        }
+
+        ::shared_lib::__private::inventory::submit! {
+            ::shared_lib::database::indexes::RegisteredModel(
+                ::shared_lib::database::indexes::CollectionIndexes::of::<#name>,
+            )
+        }
+    })
+}
+
+fn parse_options(input: &DeriveInput) -> syn::Result<ModelOptions> {
+    let mut options = ModelOptions::default();
+
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("model"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("collection") {
+                options.collection = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("timestamps") {
+                options.timestamps = true;
+            } else if meta.path.is_ident("append_only") {
+                options.append_only = true;
+            } else if meta.path.is_ident("index") {
+                options.compound_indexes.push(parse_index_keys(&meta)?);
+            } else {
+                return Err(meta.error("expected collection, timestamps, index or append_only"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Model can only be derived for structs",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Model needs a struct with named fields",
+        ));
+    };
+
+    for field in &fields.named {
+        let field_name = field
+            .ident
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+
+        for attribute in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("model"))
+        {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("unique") {
+                    options.unique_fields.push(field_name.clone());
+                } else if meta.path.is_ident("text") {
+                    options.text_fields.push(field_name.clone());
+                } else if meta.path.is_ident("ttl") {
+                    let seconds = meta.value()?.parse::<LitInt>()?.base10_parse::<u64>()?;
+                    options.ttl_fields.push((field_name.clone(), seconds));
+                } else {
+                    return Err(meta.error("expected unique, text or ttl"));
+                }
+
+                Ok(())
+            })?;
+        }
+    }
+
+    Ok(options)
+}
+
+/// Reads `index(field = 1, other = -1)`.
+fn parse_index_keys(meta: &ParseNestedMeta) -> syn::Result<Vec<(String, i32)>> {
+    let mut keys = Vec::new();
+
+    meta.parse_nested_meta(|key| {
+        let field = key
+            .path
+            .get_ident()
+            .map(ToString::to_string)
+            .ok_or_else(|| key.error("expected a field name"))?;
+
+        let value = key.value()?;
+        let is_descending = value.parse::<Option<Token![-]>>()?.is_some();
+        let direction = value.parse::<LitInt>()?.base10_parse::<i32>()?;
+
+        keys.push((field, if is_descending { -direction } else { direction }));
+
+        Ok(())
+    })?;
+
+    if keys.is_empty() {
+        return Err(meta.error("an index needs at least one field"));
+    }
+
+    Ok(keys)
+}
+
+fn index_tokens(options: &ModelOptions) -> Vec<TokenStream2> {
+    let mut indexes = Vec::new();
+
+    for field in &options.unique_fields {
+        indexes.push(quote! { ::shared_lib::database::indexes::unique_index(#field) });
+    }
+
+    if !options.text_fields.is_empty() {
+        let fields = &options.text_fields;
+        indexes.push(quote! { ::shared_lib::database::indexes::text_index(&[#(#fields),*]) });
+    }
+
+    for keys in &options.compound_indexes {
+        let (fields, directions): (Vec<_>, Vec<_>) = keys.iter().cloned().unzip();
+
+        indexes.push(quote! {
+            ::shared_lib::database::indexes::compound_index(
+                ::shared_lib::__private::mongodb::bson::doc! { #(#fields: #directions),* }
+            )
+        });
+    }
+
+    for (field, seconds) in &options.ttl_fields {
+        indexes.push(quote! {
+            ::shared_lib::database::indexes::ttl_index(
+                #field,
+                ::std::time::Duration::from_secs(#seconds),
+            )
+        });
+    }
+
+    indexes
+}
+
+fn save_tokens(options: &ModelOptions) -> TokenStream2 {
+    if !options.timestamps {
+        return quote! { ::shared_lib::database::queries::insert(database, self).await };
+    }
+
+    quote! {
+        let now = ::shared_lib::__private::chrono::Utc::now();
+        let mut model = ::std::clone::Clone::clone(self);
+
+        model.created_at.get_or_insert(now);
+        model.updated_at.get_or_insert(now);
+
+        ::shared_lib::database::queries::insert(database, &model).await
+    }
+}
+
+fn write_tokens(options: &ModelOptions) -> (TokenStream2, TokenStream2, TokenStream2) {
+    if options.append_only {
+        let error = quote! {
+            let _ = (database, filter);
+            ::std::result::Result::Err(::shared_lib::database::queries::append_only_error(
+                &<Self as ::shared_lib::traits::model_traits::ModelTraits>::get_struct_name_as_plural_string(),
+            ))
+        };
+        let update_error = quote! {
+            let _ = update;
+            #error
+        };
+
+        return (update_error.clone(), update_error, error);
+    }
+
+    let update = match options.timestamps {
+        true => quote! { ::shared_lib::database::queries::touch(update) },
+        false => quote! { update },
+    };
+
+    (
+        quote! {
+            ::shared_lib::database::queries::update_one::<Self>(database, filter, #update).await
+        },
+        quote! {
+            ::shared_lib::database::queries::update_many::<Self>(database, filter, #update).await
+        },
+        quote! {
+            ::shared_lib::database::queries::delete_one::<Self>(database, filter).await
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn expanded(input: DeriveInput) -> String {
+        expand(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn collection_defaults_to_the_plural_struct_name() {
+        let output = expanded(parse_quote! {
+            struct RefreshToken {
+                token_hash: String,
+            }
+        });
+        assert!(output.contains(r#""refreshtokens" . to_owned ()"#));
+
+        let output = expanded(parse_quote! {
+            #[model(collection = "audit_events")]
+            struct AuditEvent {
+                action: String,
+            }
+        });
+        assert!(output.contains(r#""audit_events" . to_owned ()"#));
+        assert!(output.contains("inventory :: submit !"));
+    }
+
+    #[test]
+    fn index_reads_fields_and_directions() {
+        let input: DeriveInput = parse_quote! {
+            #[model(index(user_id = 1, created_at = -1))]
+            #[model(index(slug = 1))]
+            struct Post {
+                #[model(unique)]
+                slug: String,
+                user_id: String,
+                created_at: String,
+            }
+        };
+
+        let options = parse_options(&input).unwrap();
+        assert_eq!(
+            options.compound_indexes,
+            [
+                vec![("user_id".to_owned(), 1), ("created_at".to_owned(), -1)],
+                vec![("slug".to_owned(), 1)],
+            ]
+        );
+        assert_eq!(options.unique_fields, ["slug"]);
+
+        let output = expanded(input);
+        assert_eq!(output.matches("indexes :: compound_index").count(), 2);
+        assert_eq!(output.matches("indexes :: unique_index").count(), 1);
+    }
+
+    #[test]
+    fn append_only_models_cannot_be_updated_or_deleted() {
+        let output = expanded(parse_quote! {
+            #[model(append_only)]
+            struct AuditEvent {
+                action: String,
+            }
+        });
+
+        assert_eq!(output.matches("append_only_error").count(), 3);
+        assert!(!output.contains("queries :: update_one"));
+        assert!(!output.contains("queries :: delete_one"));
+
+        let output = expanded(parse_quote! {
+            struct Post {
+                slug: String,
+            }
+        });
+        assert!(!output.contains("append_only_error"));
+    }
+
+    #[test]
+    fn unknown_options_and_empty_indexes_are_rejected() {
+        let error = |input: DeriveInput| expand(input).err().unwrap().to_string();
+
+        assert_eq!(
+            error(parse_quote! {
+                #[model(collection = "posts", soft_delete)]
+                struct Post {
+                    slug: String,
+                }
+            }),
+            "expected collection, timestamps, index or append_only"
+        );
+        assert!(expand(parse_quote! {
+            #[model(index())]
+            struct Post {
+                slug: String,
+            }
+        })
+        .is_err());
+        assert_eq!(
+            error(parse_quote! {
+                enum Post {
+                    Draft,
+                }
+            }),
+            "Model can only be derived for structs"
+        );
+    }
+}